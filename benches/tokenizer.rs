@@ -0,0 +1,74 @@
+//! Throughput of the tokenizer and the parser on test_6.html (the first web page, a mix of text,
+//! tags and attributes) repeated until it's a few megabytes long.
+//!
+//! The fast path for plain text is measured on its own by tokenizing the page's text with the
+//! markup taken out, once from the data state, which has the fast path, and once from the RCDATA
+//! state, which reads the same text one character at a time and ends up with the same tokens.
+//!
+//! Run with `cargo bench`. There's no benchmarking framework, each benchmark is run a few times
+//! and the fastest run is reported.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use wonder_land::alice::{HTMLParser, HTMLToken, HTMLTokenizer, InitialState};
+
+const COPIES: usize = 2000;
+const RUNS: usize = 10;
+
+fn main() {
+    let page = include_str!("../test_6.html");
+    let input = page.repeat(COPIES);
+
+    bench("tokenize", &input, |input| {
+        let mut tokens = 0;
+        for token in HTMLTokenizer::new(input) {
+            black_box(&token);
+            tokens += 1;
+        }
+        tokens
+    });
+
+    bench("tokenize (bytes)", &input, |input| {
+        HTMLTokenizer::from_bytes(input.as_bytes(), None)
+            .filter(|token| !matches!(token, HTMLToken::EndOfFile))
+            .count()
+    });
+
+    bench("parse", &input, |input| {
+        let document = HTMLParser::new(HTMLTokenizer::new(input)).parse();
+        document.descendants(document.document()).count()
+    });
+
+    // nothing in here ends a run of text, in the data state or in RCDATA
+    let text = page.replace(['<', '&', '\0'], " ").repeat(COPIES);
+    let fast_path = bench("text (data)", &text, |input| HTMLTokenizer::new(input).count());
+    let one_at_a_time = bench("text (RCDATA)", &text, |input| {
+        let mut tokenizer = HTMLTokenizer::new(input);
+        tokenizer.set_initial_state(InitialState::RCDATA);
+        tokenizer.count()
+    });
+    println!(
+        "{:<20} {:>8.1}x",
+        "fast path speedup",
+        one_at_a_time.as_secs_f64() / fast_path.as_secs_f64()
+    );
+}
+
+/// Prints how long the fastest run took and how many megabytes a second that is, and returns it
+fn bench(name: &str, input: &str, run: impl Fn(&str) -> usize) -> Duration {
+    let mut fastest = Duration::MAX;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        black_box(run(black_box(input)));
+        fastest = fastest.min(start.elapsed());
+    }
+    let megabytes = input.len() as f64 / (1024.0 * 1024.0);
+    println!(
+        "{:<20} {:>8.2} ms {:>10.1} MB/s",
+        name,
+        fastest.as_secs_f64() * 1000.0,
+        megabytes / fastest.as_secs_f64()
+    );
+    fastest
+}
@@ -0,0 +1,405 @@
+use std::borrow::Cow;
+use std::fmt;
+
+/// The character encodings the input byte stream can be decoded from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf16LE,
+    Utf16BE,
+    Windows1252,
+}
+
+/// How sure we are that the encoding is the right one. A tentative encoding was only guessed at
+/// (from a `<meta>` tag or the bytes themselves), while a certain one came from a byte order mark
+/// or the transport layer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Confidence {
+    Tentative,
+    Certain,
+}
+
+/// The result of the encoding sniffing algorithm
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SniffedEncoding {
+    pub encoding: Encoding,
+    pub confidence: Confidence,
+}
+
+/// Only the first 1024 bytes are looked at when prescanning for a `<meta>` tag
+const PRESCAN_LENGTH: usize = 1024;
+
+impl Encoding {
+    /// Looks up an encoding by one of its labels, e.g. "utf-8", "latin1" or "UTF-16LE". See
+    /// https://encoding.spec.whatwg.org/#concept-encoding-get
+    pub fn for_label(label: &str) -> Option<Encoding> {
+        let label = label
+            .trim_matches(|character: char| is_ascii_whitespace(character as u8))
+            .to_ascii_lowercase();
+        let encoding = match label.as_str() {
+            "unicode-1-1-utf-8" | "unicode11utf8" | "unicode20utf8" | "utf-8" | "utf8"
+            | "x-unicode20utf8" => Encoding::Utf8,
+            "csunicode" | "iso-10646-ucs-2" | "ucs-2" | "unicode" | "unicodefeff" | "utf-16"
+            | "utf-16le" => Encoding::Utf16LE,
+            "unicodefffe" | "utf-16be" => Encoding::Utf16BE,
+            "ansi_x3.4-1968" | "ascii" | "cp1252" | "cp819" | "csisolatin1" | "ibm819"
+            | "iso-8859-1" | "iso-ir-100" | "iso8859-1" | "iso88591" | "iso_8859-1"
+            | "iso_8859-1:1987" | "l1" | "latin1" | "us-ascii" | "windows-1252" | "x-cp1252" => {
+                Encoding::Windows1252
+            }
+            _ => return None,
+        };
+        Some(encoding)
+    }
+
+    /// The canonical name of the encoding
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16LE => "UTF-16LE",
+            Encoding::Utf16BE => "UTF-16BE",
+            Encoding::Windows1252 => "windows-1252",
+        }
+    }
+
+    /// Decodes the whole byte stream. A byte order mark at the start wins over this encoding and
+    /// is not part of the output. Bytes that aren't valid in the encoding become U+FFFD. Nothing
+    /// is copied when the bytes are already valid UTF-8 that decodes to the same thing
+    pub fn decode<'a>(&self, bytes: &'a [u8]) -> Cow<'a, str> {
+        let (encoding, bytes) = match bom_sniff(bytes) {
+            Some((encoding, bom_length)) => (encoding, &bytes[bom_length..]),
+            None => (*self, bytes),
+        };
+        match encoding {
+            Encoding::Utf8 => String::from_utf8_lossy(bytes),
+            Encoding::Utf16LE => Cow::Owned(decode_utf16(bytes, u16::from_le_bytes)),
+            Encoding::Utf16BE => Cow::Owned(decode_utf16(bytes, u16::from_be_bytes)),
+            // ASCII means the same thing in windows-1252 and UTF-8
+            Encoding::Windows1252 if bytes.is_ascii() => String::from_utf8_lossy(bytes),
+            Encoding::Windows1252 => Cow::Owned(
+                bytes
+                    .iter()
+                    .map(|byte| windows_1252_replacement(*byte as u32).unwrap_or(*byte as char))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Works out which encoding the byte stream is in, following
+/// https://html.spec.whatwg.org/multipage/parsing.html#encoding-sniffing-algorithm
+///
+/// In order: a byte order mark, the encoding the transport layer says the document is in (e.g.
+/// the charset in a Content-Type header), a `<meta>` tag in the first 1024 bytes and finally a
+/// guess of UTF-8 if every byte is valid UTF-8, or windows-1252 if not
+pub fn sniff_encoding(bytes: &[u8], transport_layer_encoding: Option<&str>) -> SniffedEncoding {
+    if let Some((encoding, _)) = bom_sniff(bytes) {
+        return SniffedEncoding {
+            encoding,
+            confidence: Confidence::Certain,
+        };
+    }
+
+    if let Some(encoding) = transport_layer_encoding.and_then(Encoding::for_label) {
+        return SniffedEncoding {
+            encoding,
+            confidence: Confidence::Certain,
+        };
+    }
+
+    if let Some(encoding) = prescan(&bytes[..bytes.len().min(PRESCAN_LENGTH)]) {
+        return SniffedEncoding {
+            encoding,
+            confidence: Confidence::Tentative,
+        };
+    }
+
+    // the spec leaves the default up to the user agent. windows-1252 is what most legacy content
+    // without any encoding information was written in, but anything that happens to be valid
+    // UTF-8 almost certainly is UTF-8
+    let encoding = if std::str::from_utf8(bytes).is_ok() {
+        Encoding::Utf8
+    } else {
+        Encoding::Windows1252
+    };
+    SniffedEncoding {
+        encoding,
+        confidence: Confidence::Tentative,
+    }
+}
+
+/// Returns the encoding the byte order mark at the start of the stream stands for, and how many
+/// bytes long the byte order mark is
+fn bom_sniff(bytes: &[u8]) -> Option<(Encoding, usize)> {
+    if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        Some((Encoding::Utf8, 3))
+    } else if bytes.starts_with(&[0xFE, 0xFF]) {
+        Some((Encoding::Utf16BE, 2))
+    } else if bytes.starts_with(&[0xFF, 0xFE]) {
+        Some((Encoding::Utf16LE, 2))
+    } else {
+        None
+    }
+}
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> String {
+    let code_units = bytes
+        .chunks_exact(2)
+        .map(|pair| from_bytes([pair[0], pair[1]]));
+    let mut output: String = char::decode_utf16(code_units)
+        .map(|character| character.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect();
+    if bytes.len() % 2 == 1 {
+        // a lone byte at the end is half a code unit
+        output.push(char::REPLACEMENT_CHARACTER);
+    }
+    output
+}
+
+/// Looks for a `<meta charset>` or `<meta http-equiv="content-type" content="...">` tag, see
+/// https://html.spec.whatwg.org/multipage/parsing.html#prescan-a-byte-stream-to-determine-its-encoding
+fn prescan(bytes: &[u8]) -> Option<Encoding> {
+    let mut position = 0;
+    while position < bytes.len() {
+        let rest = &bytes[position..];
+        if rest.starts_with(b"<!--") {
+            // skip to the end of the comment. The dashes of "<!--" count towards the "-->", so
+            // "<!-->" is a whole comment
+            let end = (position + 4..bytes.len())
+                .find(|&index| bytes[index] == b'>' && &bytes[index - 2..index] == b"--")?;
+            position = end + 1;
+        } else if starts_with_ignoring_ascii_case(rest, b"<meta")
+            && rest
+                .get(5)
+                .is_some_and(|byte| is_ascii_whitespace(*byte) || *byte == b'/')
+        {
+            position += 6;
+            if let Some(encoding) = prescan_meta_tag(bytes, &mut position) {
+                return Some(encoding);
+            }
+        } else if (rest.len() >= 2 && rest[0] == b'<' && rest[1].is_ascii_alphabetic())
+            || (rest.len() >= 3 && rest.starts_with(b"</") && rest[2].is_ascii_alphabetic())
+        {
+            // some other tag, which is skipped along with its attributes
+            while position < bytes.len()
+                && !is_ascii_whitespace(bytes[position])
+                && bytes[position] != b'>'
+            {
+                position += 1;
+            }
+            while get_an_attribute(bytes, &mut position).is_some() {}
+            position += 1;
+        } else if rest.starts_with(b"<!") || rest.starts_with(b"</") || rest.starts_with(b"<?") {
+            let end = (position + 2..bytes.len()).find(|&index| bytes[index] == b'>')?;
+            position = end + 1;
+        } else {
+            position += 1;
+        }
+    }
+    None
+}
+
+/// Goes through the attributes of a `<meta>` tag, returning the encoding it declares if it
+/// declares one. `position` starts right after "<meta "
+fn prescan_meta_tag(bytes: &[u8], position: &mut usize) -> Option<Encoding> {
+    let mut attribute_names: Vec<String> = Vec::new();
+    let mut got_pragma = false;
+    let mut need_pragma = None;
+    let mut charset = None;
+
+    while let Some((name, value)) = get_an_attribute(bytes, position) {
+        if attribute_names.contains(&name) {
+            continue;
+        }
+        match name.as_str() {
+            "http-equiv" if value == "content-type" => {
+                got_pragma = true;
+            }
+            "content" if charset.is_none() => {
+                if let Some(label) = extract_encoding_from_content(&value) {
+                    charset = Some(Encoding::for_label(&label));
+                    need_pragma = Some(true);
+                }
+            }
+            // whichever of content and charset comes first wins
+            "charset" if charset.is_none() => {
+                charset = Some(Encoding::for_label(&value));
+                need_pragma = Some(false);
+            }
+            _ => {}
+        }
+        attribute_names.push(name);
+    }
+
+    match need_pragma {
+        None => return None,
+        Some(true) if !got_pragma => return None,
+        _ => {}
+    }
+    match charset?? {
+        // a document that could be read far enough to find the meta tag can't be in UTF-16
+        Encoding::Utf16LE | Encoding::Utf16BE => Some(Encoding::Utf8),
+        encoding => Some(encoding),
+    }
+}
+
+/// Reads the next attribute of a tag, returning its lowercased name and value. Returns None
+/// once the end of the tag (or the input) is reached, see
+/// https://html.spec.whatwg.org/multipage/parsing.html#concept-get-attributes-when-sniffing
+fn get_an_attribute(bytes: &[u8], position: &mut usize) -> Option<(String, String)> {
+    let byte_at = |position: usize| bytes.get(position).copied();
+
+    while is_ascii_whitespace(byte_at(*position)?) || byte_at(*position)? == b'/' {
+        *position += 1;
+    }
+    if byte_at(*position)? == b'>' {
+        return None;
+    }
+
+    let mut name = String::new();
+    let mut value = String::new();
+
+    // attribute name
+    loop {
+        let byte = byte_at(*position)?;
+        match byte {
+            b'=' if !name.is_empty() => {
+                *position += 1;
+                break;
+            }
+            b'/' | b'>' => return Some((name, value)),
+            _ if is_ascii_whitespace(byte) => {
+                // spaces between the name and a possible "="
+                while is_ascii_whitespace(byte_at(*position)?) {
+                    *position += 1;
+                }
+                if byte_at(*position)? != b'=' {
+                    return Some((name, value));
+                }
+                *position += 1;
+                break;
+            }
+            _ => name.push(byte.to_ascii_lowercase() as char),
+        }
+        *position += 1;
+    }
+
+    // attribute value
+    while is_ascii_whitespace(byte_at(*position)?) {
+        *position += 1;
+    }
+    let byte = byte_at(*position)?;
+    match byte {
+        b'"' | b'\'' => loop {
+            *position += 1;
+            let next_byte = byte_at(*position)?;
+            if next_byte == byte {
+                *position += 1;
+                return Some((name, value));
+            }
+            value.push(next_byte.to_ascii_lowercase() as char);
+        },
+        b'>' => return Some((name, value)),
+        _ => {
+            value.push(byte.to_ascii_lowercase() as char);
+            *position += 1;
+        }
+    }
+    loop {
+        let byte = byte_at(*position)?;
+        if is_ascii_whitespace(byte) || byte == b'>' {
+            return Some((name, value));
+        }
+        value.push(byte.to_ascii_lowercase() as char);
+        *position += 1;
+    }
+}
+
+/// Pulls the encoding label out of a content attribute like "text/html; charset=utf-8", see
+/// https://html.spec.whatwg.org/multipage/urls-and-fetching.html#algorithm-for-extracting-a-character-encoding-from-a-meta-element
+fn extract_encoding_from_content(content: &str) -> Option<String> {
+    let content = content.as_bytes();
+    let mut position = 0;
+    loop {
+        let index = content[position..]
+            .windows(7)
+            .position(|window| window.eq_ignore_ascii_case(b"charset"))?;
+        position += index + 7;
+        while content.get(position).is_some_and(|byte| is_ascii_whitespace(*byte)) {
+            position += 1;
+        }
+        if content.get(position) == Some(&b'=') {
+            position += 1;
+            break;
+        }
+    }
+    while content.get(position).is_some_and(|byte| is_ascii_whitespace(*byte)) {
+        position += 1;
+    }
+
+    let rest = &content[position..];
+    match rest.first()? {
+        quote @ (b'"' | b'\'') => {
+            let end = rest[1..].iter().position(|byte| byte == quote)?;
+            Some(String::from_utf8_lossy(&rest[1..end + 1]).into_owned())
+        }
+        _ => {
+            let end = rest
+                .iter()
+                .position(|byte| is_ascii_whitespace(*byte) || *byte == b';')
+                .unwrap_or(rest.len());
+            Some(String::from_utf8_lossy(&rest[..end]).into_owned())
+        }
+    }
+}
+
+fn starts_with_ignoring_ascii_case(bytes: &[u8], prefix: &[u8]) -> bool {
+    bytes.len() >= prefix.len() && bytes[..prefix.len()].eq_ignore_ascii_case(prefix)
+}
+
+fn is_ascii_whitespace(byte: u8) -> bool {
+    matches!(byte, 0x09 | 0x0A | 0x0C | 0x0D | 0x20)
+}
+
+/// Bytes 0x80 - 0x9F mean something different in windows-1252 than the code points with the same
+/// value. This is also how numeric character references in that range get interpreted, since
+/// that's what legacy pages that use them actually meant
+pub(crate) fn windows_1252_replacement(code: u32) -> Option<char> {
+    let replacement = match code {
+        0x80 => '\u{20AC}', // EURO SIGN (€)
+        0x82 => '\u{201A}', // SINGLE LOW-9 QUOTATION MARK (‚)
+        0x83 => '\u{0192}', // LATIN SMALL LETTER F WITH HOOK (ƒ)
+        0x84 => '\u{201E}', // DOUBLE LOW-9 QUOTATION MARK („)
+        0x85 => '\u{2026}', // HORIZONTAL ELLIPSIS (…)
+        0x86 => '\u{2020}', // DAGGER (†)
+        0x87 => '\u{2021}', // DOUBLE DAGGER (‡)
+        0x88 => '\u{02C6}', // MODIFIER LETTER CIRCUMFLEX ACCENT (ˆ)
+        0x89 => '\u{2030}', // PER MILLE SIGN (‰)
+        0x8A => '\u{0160}', // LATIN CAPITAL LETTER S WITH CARON (Š)
+        0x8B => '\u{2039}', // SINGLE LEFT-POINTING ANGLE QUOTATION MARK (‹)
+        0x8C => '\u{0152}', // LATIN CAPITAL LIGATURE OE (Œ)
+        0x8E => '\u{017D}', // LATIN CAPITAL LETTER Z WITH CARON (Ž)
+        0x91 => '\u{2018}', // LEFT SINGLE QUOTATION MARK (‘)
+        0x92 => '\u{2019}', // RIGHT SINGLE QUOTATION MARK (’)
+        0x93 => '\u{201C}', // LEFT DOUBLE QUOTATION MARK (“)
+        0x94 => '\u{201D}', // RIGHT DOUBLE QUOTATION MARK (”)
+        0x95 => '\u{2022}', // BULLET (•)
+        0x96 => '\u{2013}', // EN DASH (–)
+        0x97 => '\u{2014}', // EM DASH (—)
+        0x98 => '\u{02DC}', // SMALL TILDE (˜)
+        0x99 => '\u{2122}', // TRADE MARK SIGN (™)
+        0x9A => '\u{0161}', // LATIN SMALL LETTER S WITH CARON (š)
+        0x9B => '\u{203A}', // SINGLE RIGHT-POINTING ANGLE QUOTATION MARK (›)
+        0x9C => '\u{0153}', // LATIN SMALL LIGATURE OE (œ)
+        0x9E => '\u{017E}', // LATIN SMALL LETTER Z WITH CARON (ž)
+        0x9F => '\u{0178}', // LATIN CAPITAL LETTER Y WITH DIAERESIS (Ÿ)
+        _ => return None,
+    };
+    Some(replacement)
+}
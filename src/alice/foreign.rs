@@ -0,0 +1,162 @@
+use super::Attributes;
+use crate::dom::Namespace;
+
+/// SVG element names that have uppercase letters in them. The tokenizer lowercases every tag
+/// name, so these have to be put back the way SVG spells them
+const SVG_TAG_NAMES: &[(&str, &str)] = &[
+    ("altglyph", "altGlyph"),
+    ("altglyphdef", "altGlyphDef"),
+    ("altglyphitem", "altGlyphItem"),
+    ("animatecolor", "animateColor"),
+    ("animatemotion", "animateMotion"),
+    ("animatetransform", "animateTransform"),
+    ("clippath", "clipPath"),
+    ("feblend", "feBlend"),
+    ("fecolormatrix", "feColorMatrix"),
+    ("fecomponenttransfer", "feComponentTransfer"),
+    ("fecomposite", "feComposite"),
+    ("feconvolvematrix", "feConvolveMatrix"),
+    ("fediffuselighting", "feDiffuseLighting"),
+    ("fedisplacementmap", "feDisplacementMap"),
+    ("fedistantlight", "feDistantLight"),
+    ("fedropshadow", "feDropShadow"),
+    ("feflood", "feFlood"),
+    ("fefunca", "feFuncA"),
+    ("fefuncb", "feFuncB"),
+    ("fefuncg", "feFuncG"),
+    ("fefuncr", "feFuncR"),
+    ("fegaussianblur", "feGaussianBlur"),
+    ("feimage", "feImage"),
+    ("femerge", "feMerge"),
+    ("femergenode", "feMergeNode"),
+    ("femorphology", "feMorphology"),
+    ("feoffset", "feOffset"),
+    ("fepointlight", "fePointLight"),
+    ("fespecularlighting", "feSpecularLighting"),
+    ("fespotlight", "feSpotLight"),
+    ("fetile", "feTile"),
+    ("feturbulence", "feTurbulence"),
+    ("foreignobject", "foreignObject"),
+    ("glyphref", "glyphRef"),
+    ("lineargradient", "linearGradient"),
+    ("radialgradient", "radialGradient"),
+    ("textpath", "textPath"),
+];
+
+/// SVG attribute names that have uppercase letters in them, like `viewBox`
+const SVG_ATTRIBUTE_NAMES: &[(&str, &str)] = &[
+    ("attributename", "attributeName"),
+    ("attributetype", "attributeType"),
+    ("basefrequency", "baseFrequency"),
+    ("baseprofile", "baseProfile"),
+    ("calcmode", "calcMode"),
+    ("clippathunits", "clipPathUnits"),
+    ("diffuseconstant", "diffuseConstant"),
+    ("edgemode", "edgeMode"),
+    ("filterunits", "filterUnits"),
+    ("glyphref", "glyphRef"),
+    ("gradienttransform", "gradientTransform"),
+    ("gradientunits", "gradientUnits"),
+    ("kernelmatrix", "kernelMatrix"),
+    ("kernelunitlength", "kernelUnitLength"),
+    ("keypoints", "keyPoints"),
+    ("keysplines", "keySplines"),
+    ("keytimes", "keyTimes"),
+    ("lengthadjust", "lengthAdjust"),
+    ("limitingconeangle", "limitingConeAngle"),
+    ("markerheight", "markerHeight"),
+    ("markerunits", "markerUnits"),
+    ("markerwidth", "markerWidth"),
+    ("maskcontentunits", "maskContentUnits"),
+    ("maskunits", "maskUnits"),
+    ("numoctaves", "numOctaves"),
+    ("pathlength", "pathLength"),
+    ("patterncontentunits", "patternContentUnits"),
+    ("patterntransform", "patternTransform"),
+    ("patternunits", "patternUnits"),
+    ("pointsatx", "pointsAtX"),
+    ("pointsaty", "pointsAtY"),
+    ("pointsatz", "pointsAtZ"),
+    ("preservealpha", "preserveAlpha"),
+    ("preserveaspectratio", "preserveAspectRatio"),
+    ("primitiveunits", "primitiveUnits"),
+    ("refx", "refX"),
+    ("refy", "refY"),
+    ("repeatcount", "repeatCount"),
+    ("repeatdur", "repeatDur"),
+    ("requiredextensions", "requiredExtensions"),
+    ("requiredfeatures", "requiredFeatures"),
+    ("specularconstant", "specularConstant"),
+    ("specularexponent", "specularExponent"),
+    ("spreadmethod", "spreadMethod"),
+    ("startoffset", "startOffset"),
+    ("stddeviation", "stdDeviation"),
+    ("stitchtiles", "stitchTiles"),
+    ("surfacescale", "surfaceScale"),
+    ("systemlanguage", "systemLanguage"),
+    ("tablevalues", "tableValues"),
+    ("targetx", "targetX"),
+    ("targety", "targetY"),
+    ("textlength", "textLength"),
+    ("viewbox", "viewBox"),
+    ("viewtarget", "viewTarget"),
+    ("xchannelselector", "xChannelSelector"),
+    ("ychannelselector", "yChannelSelector"),
+    ("zoomandpan", "zoomAndPan"),
+];
+
+/// Attributes that are put in a namespace on foreign elements, with the namespace and the name
+/// they go by in it: `xlink:href` becomes `href` in the XLink namespace
+const FOREIGN_ATTRIBUTES: &[(&str, Namespace, &str)] = &[
+    ("xlink:actuate", Namespace::XLink, "actuate"),
+    ("xlink:arcrole", Namespace::XLink, "arcrole"),
+    ("xlink:href", Namespace::XLink, "href"),
+    ("xlink:role", Namespace::XLink, "role"),
+    ("xlink:show", Namespace::XLink, "show"),
+    ("xlink:title", Namespace::XLink, "title"),
+    ("xlink:type", Namespace::XLink, "type"),
+    ("xml:lang", Namespace::Xml, "lang"),
+    ("xml:space", Namespace::Xml, "space"),
+    ("xmlns", Namespace::XmlNs, "xmlns"),
+    ("xmlns:xlink", Namespace::XmlNs, "xlink"),
+];
+
+/// The SVG spelling of a tag name, e.g. `foreignObject` for `foreignobject`
+pub fn adjust_svg_tag_name(tag_name: &str) -> &str {
+    SVG_TAG_NAMES
+        .iter()
+        .find(|(lowercase, _)| *lowercase == tag_name)
+        .map_or(tag_name, |(_, adjusted)| adjusted)
+}
+
+pub fn adjust_svg_attributes(attributes: &mut Attributes) {
+    for attribute in attributes {
+        if let Some((_, adjusted)) = SVG_ATTRIBUTE_NAMES
+            .iter()
+            .find(|(lowercase, _)| *lowercase == attribute.key)
+        {
+            attribute.key = (*adjusted).into();
+        }
+    }
+}
+
+/// MathML only has the one attribute with an uppercase letter in it
+pub fn adjust_mathml_attributes(attributes: &mut Attributes) {
+    for attribute in attributes {
+        if attribute.key == "definitionurl" {
+            attribute.key = "definitionURL".into();
+        }
+    }
+}
+
+pub fn adjust_foreign_attributes(attributes: &mut Attributes) {
+    for attribute in attributes {
+        if let Some((_, namespace, local_name)) = FOREIGN_ATTRIBUTES
+            .iter()
+            .find(|(qualified_name, _, _)| *qualified_name == attribute.key)
+        {
+            attribute.key = (*local_name).into();
+            attribute.namespace = Some(*namespace);
+        }
+    }
+}
@@ -1,24 +1,64 @@
-use std::collections::HashMap;
+// state and token names follow the html spec, which spells them in uppercase (DOCTYPE, RCDATA...)
+#![allow(clippy::upper_case_acronyms)]
+
+mod encoding;
+mod foreign;
+mod named_character_references;
+mod observer;
+mod parse_error;
+mod parser;
+mod quirks;
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use memchr::memchr3;
+
+use crate::dom::Namespace;
+use encoding::windows_1252_replacement;
+use named_character_references::{LONGEST_NAME_LENGTH, NAMED_CHARACTER_REFERENCES};
+pub use encoding::{sniff_encoding, Confidence, Encoding, SniffedEncoding};
+pub use observer::{DebugTracer, TokenizerObserver};
+pub use parse_error::{ParseError, ParseErrorReport, SourcePosition, SourceSpan};
+pub use parser::{HTMLParser, InsertionMode};
+pub use quirks::quirks_mode;
 
-// type Attributes = HashMap<String, String>;
 #[derive(Debug, Clone)]
-struct Attribute {
-    key: String,
-    value: String
+pub struct Attribute<'a> {
+    pub key: Cow<'a, str>,
+    pub value: Cow<'a, str>,
+    pub original_key: Option<Cow<'a, str>>, // the key as written in the source, see set_preserve_original_case
+    pub key_span: SourceSpan,
+    pub value_span: Option<SourceSpan>, // None when the attribute has no value, quotes aren't included
+    pub namespace: Option<Namespace>, // only set by the tree construction stage, e.g. for xlink:href on svg elements
 }
 
-type Attributes = Vec<Attribute>;
+pub type Attributes<'a> = Vec<Attribute<'a>>;
 
-macro_rules! eof_reached {
-    ($msg:expr) => {
-        println!("{:?}", $msg);
-        break;
-    };
+impl Attribute<'_> {
+    /// Copies anything borrowed from the input, for keeping the attribute around after the input
+    /// is gone
+    pub fn into_owned(self) -> Attribute<'static> {
+        Attribute {
+            key: Cow::Owned(self.key.into_owned()),
+            value: Cow::Owned(self.value.into_owned()),
+            original_key: self.original_key.map(|original_key| Cow::Owned(original_key.into_owned())),
+            key_span: self.key_span,
+            value_span: self.value_span,
+            namespace: self.namespace,
+        }
+    }
 }
 
-macro_rules! unknown_state_reached {
-    ($last_state:expr) => {
-        println!("unknown state encountered: {:?}", $last_state);
+/// Ends the tokenization process by emitting the end-of-file token. Any character data that
+/// hasn't been emitted yet goes out before it, and it's the last token the tokenizer emits
+macro_rules! eof_reached {
+    ($tokenizer:expr) => {
+        if let Some(observer) = &mut $tokenizer.observer {
+            observer.end_of_file_reached();
+        }
+        $tokenizer.emit_token(HTMLToken::EndOfFile);
         break;
     };
 }
@@ -28,537 +68,1576 @@ macro_rules! unknown_state_reached {
 /// immediately after the current_input_character. In most cases, this distinction isn't important,
 /// but it becomes important once we start getting into reconsuming characters
 ///
-
-pub struct HTMLTokenizer {
+/// The tokenizer borrows its input, and so do the tokens it emits: any text in a token that is
+/// exactly what's in the input (which is most of it) is a slice of the input rather than a copy
+pub struct HTMLTokenizer<'a> {
     reconsume: bool,
     current_input_character: Option<char>,
     next_input_character: Option<char>,
     state: HTMLTokenizerState,
     return_state: Option<HTMLTokenizerState>, // None by default
-    input_stream: Vec<char>,
-    cursor: Option<usize>,
-    tokens: Vec<HTMLToken>,
-    current_doctype_token: Option<DOCTYPE>,
-    current_tag_token: Option<Tag>,
-    current_comment_token: Option<Comment>,
+    input: Cow<'a, str>, // newlines are normalized as the characters are consumed
+    tokens: VecDeque<HTMLToken<'a>>, // tokens that have been emitted but not handed out yet
+    end_of_file_emitted: bool,
+    pending_characters: Cow<'a, str>, // character data that will be emitted as one Character token
+    pending_characters_span: SourceSpan,
+    split_whitespace_runs: bool,
+    preserve_original_case: bool,
+    in_foreign_content: bool, // whether the adjusted current node is an svg or math element
+    current_doctype_token: Option<DOCTYPE<'a>>,
+    current_tag_token: Option<Tag<'a>>,
+    current_comment_token: Option<Comment<'a>>,
+    temporary_buffer: String,
+    character_reference_code: u32,
+    last_start_tag_name: Option<Cow<'a, str>>,
+    current_attribute_is_duplicate: bool, // dropped once the next attribute starts or the tag is emitted
+    current_position: SourcePosition, // position of the current_input_character
+    next_position: SourcePosition,    // position of the next_input_character
+    token_start: SourcePosition, // where the token being tokenized starts, see update_token_start
+    parse_errors: Vec<ParseErrorReport>,
+    observer: Option<Box<dyn TokenizerObserver>>,
+    encoding: Option<SniffedEncoding>, // None when the input was already decoded
 }
 
 #[derive(Debug)]
-pub enum HTMLToken {
-    Doctype(DOCTYPE),
-    Tag(Tag),
-    Comment(Comment),
-    Character(Character),
+pub enum HTMLToken<'a> {
+    Doctype(DOCTYPE<'a>),
+    Tag(Tag<'a>),
+    Comment(Comment<'a>),
+    Character(Character<'a>),
     EndOfFile,
 }
 
-#[derive(Clone, Debug)]
-pub struct DOCTYPE {
-    name: Option<String>,              // None by default which is different from ""
-    public_identifier: Option<String>, // None by default which is different from ""
-    system_identifier: Option<String>, // None by default which is different from ""
-    force_quirks: bool,                // false by default
+#[derive(Clone, Debug, Default)]
+pub struct DOCTYPE<'a> {
+    pub name: Option<Cow<'a, str>>,              // None by default which is different from ""
+    pub public_identifier: Option<Cow<'a, str>>, // None by default which is different from ""
+    pub system_identifier: Option<Cow<'a, str>>, // None by default which is different from ""
+    pub force_quirks: bool,                      // false by default
+    pub original_name: Option<Cow<'a, str>>,     // the name as written in the source, see set_preserve_original_case
+    pub span: SourceSpan,
 }
 
 #[derive(Clone, Debug)]
-pub enum Tag {
+pub enum Tag<'a> {
     StartTag {
-        tag_name: String,
-        self_closing: bool,     // false by default
-        attributes: Attributes, // empty by default
+        tag_name: Cow<'a, str>,
+        self_closing: bool,                      // false by default
+        attributes: Attributes<'a>,              // empty by default
+        original_tag_name: Option<Cow<'a, str>>, // the name as written in the source, see set_preserve_original_case
+        span: SourceSpan,
     },
     EndTag {
-        tag_name: String,
-        self_closing: bool,     // false by default
-        attributes: Attributes, // empty by default
+        tag_name: Cow<'a, str>,
+        self_closing: bool,                      // false by default
+        attributes: Attributes<'a>,              // empty by default
+        original_tag_name: Option<Cow<'a, str>>, // the name as written in the source, see set_preserve_original_case
+        span: SourceSpan,
     },
 }
 
 #[derive(Clone, Debug)]
-pub struct Comment {
-    data: String,
+pub struct Comment<'a> {
+    pub data: Cow<'a, str>,
+    pub span: SourceSpan,
 }
 
 #[derive(Debug)]
-pub struct Character {
-    data: String,
+pub struct Character<'a> {
+    pub data: Cow<'a, str>,
+    pub span: SourceSpan,
+}
+
+impl HTMLToken<'_> {
+    /// Where in the input the token was read from. For a character token that's the whole run of
+    /// text, character references included. The end-of-file token doesn't have a span
+    pub fn span(&self) -> Option<SourceSpan> {
+        match self {
+            HTMLToken::Doctype(DOCTYPE { span, .. })
+            | HTMLToken::Tag(Tag::StartTag { span, .. } | Tag::EndTag { span, .. })
+            | HTMLToken::Comment(Comment { span, .. })
+            | HTMLToken::Character(Character { span, .. }) => Some(*span),
+            HTMLToken::EndOfFile => None,
+        }
+    }
 }
 
-impl HTMLTokenizer {
-    pub fn new(input_stream: &Vec<char>) -> Self {
+impl<'a> HTMLTokenizer<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self::with_input(Cow::Borrowed(input))
+    }
+
+    fn with_input(input: Cow<'a, str>) -> Self {
         Self {
             state: HTMLTokenizerState::Data,
             reconsume: false,
             return_state: None,
-            input_stream: input_stream.clone(),
-            cursor: Some(0),
-            tokens: Vec::new(),
+            next_input_character: input_character_at(&input, 0),
+            input,
+            tokens: VecDeque::new(),
+            end_of_file_emitted: false,
+            pending_characters: Cow::Borrowed(""),
+            pending_characters_span: SourceSpan::default(),
+            split_whitespace_runs: false,
+            preserve_original_case: false,
+            in_foreign_content: false,
             current_input_character: None,
-            next_input_character: input_stream.clone().get(0).copied(), // char at index 0;
             current_doctype_token: None,
             current_tag_token: None,
             current_comment_token: None,
+            temporary_buffer: String::new(),
+            character_reference_code: 0,
+            last_start_tag_name: None,
+            current_attribute_is_duplicate: false,
+            current_position: SourcePosition::default(),
+            next_position: SourcePosition::default(),
+            token_start: SourcePosition::default(),
+            parse_errors: Vec::new(),
+            observer: None,
+            encoding: None,
         }
     }
 
-    fn consume_next_input_character(&mut self) -> &Option<char> {
+    /// Creates a tokenizer for raw bytes, such as a file read from disk or a response body. The
+    /// encoding is sniffed from the bytes themselves, unless there's a byte order mark or the
+    /// transport layer says what it is, e.g. through the charset in a Content-Type header.
+    ///
+    /// Tokens only borrow from the bytes when they're UTF-8 (or plain ASCII). In any other
+    /// encoding the input has to be decoded into a new string first, which the tokens can't
+    /// borrow from, so all of their text is copied
+    pub fn from_bytes(bytes: &'a [u8], transport_layer_encoding: Option<&str>) -> Self {
+        let sniffed_encoding = sniff_encoding(bytes, transport_layer_encoding);
+        let input = sniffed_encoding.encoding.decode(bytes);
+        let mut tokenizer = Self::with_input(input);
+        tokenizer.encoding = Some(sniffed_encoding);
+        tokenizer
+    }
+
+    /// The encoding the input was decoded from and how sure we are that it's the right one. This
+    /// is None for a tokenizer created from a string with `new`
+    pub fn encoding(&self) -> Option<SniffedEncoding> {
+        self.encoding
+    }
+
+    fn consume_next_input_character(&mut self) -> Option<char> {
         if self.reconsume {
-            println!("reconsume @ {:?}", self.state);
-            println!(
-                "c_i_c and n_i_c : {:?} {:?}",
-                self.current_input_character, self.next_input_character
-            );
-            // Dont advance the cursor. Just return current_input_character as is.
+            if let Some(observer) = &mut self.observer {
+                observer.character_reconsumed(
+                    self.state,
+                    self.current_input_character,
+                    self.next_input_character,
+                );
+            }
+            // Dont advance the position. Just return current_input_character as is.
 
             // reset self.reconsume
             self.reconsume = false;
         } else {
             // set current_input_character to whatever the value of next_input_character was
-            self.current_input_character = self.next_input_character.clone();
-            // advance the cursor by 1
-            match self.cursor {
-                None => self.cursor = Some(0),
-                Some(value) => self.cursor = Some(value + 1),
-            };
+            self.current_input_character = self.next_input_character;
+            self.current_position = self.next_position;
+            if let Some(current_input_character) = self.current_input_character {
+                self.next_position = self.current_position.advance(current_input_character);
+                if self.input[self.current_position.offset..].starts_with("\r\n") {
+                    // the pair was read as a single line feed, skip over the LF too
+                    self.next_position.offset += 1;
+                }
+                self.check_input_character(current_input_character);
+            }
             // then next_input_character should be the next character in the input stream
-            self.next_input_character = self.input_stream.get(self.cursor.unwrap()).copied();
+            self.next_input_character = input_character_at(&self.input, self.next_position.offset);
+        }
+        self.update_token_start();
+        // return the current input character
+        self.current_input_character
+    }
+
+    /// Every character consumed in one of the text states could be the start of a token: a `<`
+    /// starts a tag or comment, a `&` starts a character reference and anything else is text. So
+    /// that's where the next token starts, and it only moves again once the tokenizer is back in
+    /// a text state. Reconsuming counts, since the character then starts something new
+    fn update_token_start(&mut self) {
+        if self.state.is_text_state() {
+            self.token_start = self.current_position;
+        }
+    }
+
+    /// The span of the token that's being emitted, from where it started up to and including the
+    /// current input character
+    fn current_token_span(&self) -> SourceSpan {
+        SourceSpan {
+            start: self.token_start,
+            end: self.next_position,
+        }
+    }
+
+    /// The fast path for the states that mostly just pass characters along: the input from the
+    /// next input character up to the first byte the state has to look at itself, which is any of
+    /// the three delimiters or anything the slow path does more with than pass it along (see
+    /// `needs_a_closer_look`). None if there's nothing to take, or if the current input character
+    /// is about to be reconsumed
+    fn plain_run(&self, first: u8, second: u8, third: u8) -> Option<Range<usize>> {
+        if self.reconsume {
+            return None;
+        }
+        let start = self.next_position.offset;
+        let rest = &self.input.as_bytes()[start..];
+        let end = memchr3(first, second, third, rest).unwrap_or(rest.len());
+        let end = rest[..end]
+            .iter()
+            .position(|byte| needs_a_closer_look(*byte))
+            .unwrap_or(end);
+        (end > 0).then_some(start..start + end)
+    }
+
+    /// Consumes every character in the run, leaving the tokenizer where it would be if they had
+    /// been consumed one at a time
+    fn consume_run(&mut self, run: Range<usize>) {
+        let text = &self.input[run];
+        let Some(last_character) = text.chars().next_back() else {
+            return;
+        };
+        self.current_position = self
+            .next_position
+            .advance_over(&text[..text.len() - last_character.len_utf8()]);
+        self.current_input_character = Some(last_character);
+        self.next_position = self.current_position.advance(last_character);
+        self.next_input_character = input_character_at(&self.input, self.next_position.offset);
+        self.update_token_start();
+    }
+
+    /// Emits a run of text in the data state as character tokens, like `emit_character_token`
+    /// does for a single character
+    fn emit_character_run(&mut self, mut run: Range<usize>) {
+        let bytes = self.input.as_bytes();
+        if self.split_whitespace_runs {
+            // only take as much as is on the same side of the whitespace split as the first byte
+            let is_whitespace = is_ascii_whitespace(bytes[run.start] as u32);
+            if let Some(length) = bytes[run.clone()]
+                .iter()
+                .position(|byte| is_ascii_whitespace(*byte as u32) != is_whitespace)
+            {
+                run.end = run.start + length;
+            }
+            if let Some(last_character) = self.pending_characters.chars().next_back() {
+                if is_ascii_whitespace(last_character as u32) != is_whitespace {
+                    self.pending_characters_span.end = self.next_position;
+                    self.flush_pending_characters();
+                }
+            }
+        }
+        if self.pending_characters.is_empty() {
+            self.pending_characters_span.start = self.next_position;
+        }
+        push_str_to_text(
+            &self.input,
+            &mut self.pending_characters,
+            &self.input[run.clone()],
+            self.next_position.offset,
+        );
+        self.consume_run(run);
+        self.pending_characters_span.end = self.next_position;
+    }
+
+    fn append_run_to_current_attribute_value(&mut self, run: Range<usize>) {
+        if let Some(Tag::StartTag { attributes, .. } | Tag::EndTag { attributes, .. }) =
+            &mut self.current_tag_token
+        {
+            if let Some(current_attribute) = attributes.last_mut() {
+                push_str_to_text(
+                    &self.input,
+                    &mut current_attribute.value,
+                    &self.input[run.clone()],
+                    run.start,
+                );
+            }
+        }
+        self.consume_run(run);
+    }
+
+    fn append_run_to_current_comment(&mut self, run: Range<usize>) {
+        if let Some(comment_token) = &mut self.current_comment_token {
+            push_str_to_text(&self.input, &mut comment_token.data, &self.input[run.clone()], run.start);
+        }
+        self.consume_run(run);
+    }
+
+    /// Reports control characters and noncharacters the first time they're consumed. The spec
+    /// also has these report surrogates, but a Rust `char` can never be one: decoding already
+    /// turned any lone surrogate into U+FFFD
+    fn check_input_character(&mut self, character: char) {
+        let code = character as u32;
+        if is_control(code) && !is_ascii_whitespace(code) && code != 0x00 {
+            self.emit_parse_error(ParseError::ControlCharacterInInputStream);
+        } else if is_noncharacter(code) {
+            self.emit_parse_error(ParseError::NoncharacterInInputStream);
+        }
+    }
+
+    /// The parse errors the tokenizer has run into so far, in the order they happened
+    pub fn parse_errors(&self) -> &[ParseErrorReport] {
+        &self.parse_errors
+    }
+
+    /// Contiguous character data is emitted as a single `Character` token. With this turned on, a
+    /// run is also cut wherever it goes from whitespace to non-whitespace characters or back, so
+    /// that every `Character` token is either all whitespace or has none at all
+    pub fn set_split_whitespace_runs(&mut self, split_whitespace_runs: bool) {
+        self.split_whitespace_runs = split_whitespace_runs;
+    }
+
+    /// Tag names, attribute names and DOCTYPE names are always lowercased for ASCII letters, as
+    /// the spec requires. With this turned on, they are also kept as they were written in the
+    /// source, in `original_tag_name`, `original_key` and `original_name`, for tools like
+    /// formatters that have to reproduce the source faithfully
+    pub fn set_preserve_original_case(&mut self, preserve_original_case: bool) {
+        self.preserve_original_case = preserve_original_case;
+    }
+
+    /// `<![CDATA[` only starts a CDATA section in svg and math content, and is a bogus comment
+    /// anywhere else. The tokenizer can't tell where it is in the tree, so the tree construction
+    /// stage keeps it up to date with whether the adjusted current node is a foreign element
+    pub fn set_in_foreign_content(&mut self, in_foreign_content: bool) {
+        self.in_foreign_content = in_foreign_content;
+    }
+
+    /// Makes the tokenizer act as if a start tag with this name was the last one it emitted, which
+    /// decides whether an end tag in the RCDATA, RAWTEXT and script data states closes them. For
+    /// starting the tokenizer part way through a document, like the html5lib tests do
+    pub fn set_last_start_tag_name(&mut self, tag_name: &str) {
+        self.last_start_tag_name = Some(Cow::Owned(tag_name.to_string()));
+    }
+
+    /// Sets something to be told about state switches, reconsumed characters, emitted tokens and
+    /// parse errors as the tokenizer runs. Use `DebugTracer` to print all of that to stdout
+    pub fn set_observer(&mut self, observer: Box<dyn TokenizerObserver>) {
+        self.observer = Some(observer);
+    }
+
+    fn emit_parse_error(&mut self, error: ParseError) {
+        let report = ParseErrorReport {
+            error,
+            position: self.current_position,
+        };
+        if let Some(observer) = &mut self.observer {
+            observer.parse_error(&report);
         }
-        // return a reference to the current input character
-        return &self.current_input_character;
+        self.parse_errors.push(report);
     }
 
-    fn switch_state(&mut self, state: HTMLTokenizerState) {
+    /// Puts the tokenizer in one of the states it can start in, e.g. RCDATA to tokenize the
+    /// contents of a `<textarea>` on their own. Together with `set_last_start_tag_name` this is
+    /// for starting the tokenizer part way through a document, like the html5lib tests do
+    pub fn set_initial_state(&mut self, state: InitialState) {
+        self.switch_state(state.into());
+    }
+
+    /// Switches the tokenizer to another state. Apart from the tokenizer itself, this is used by
+    /// the tree construction stage, which for example has to switch the tokenizer to the RCDATA
+    /// state after a `<title>` start tag or the RAWTEXT state after a `<style>` start tag
+    pub(crate) fn switch_state(&mut self, state: HTMLTokenizerState) {
+        if let Some(observer) = &mut self.observer {
+            observer.state_switched(self.state, state);
+        }
         self.state = state;
     }
 
-    pub fn run(&mut self) {
+    /// Returns the next token in the input. Tokens are produced lazily: the tokenizer only runs for
+    /// as long as it takes to emit the next one. The last token is always
+    /// `HTMLToken::EndOfFile`, after which this returns `None`
+    pub fn next_token(&mut self) -> Option<HTMLToken<'a>> {
+        if self.tokens.is_empty() && !self.end_of_file_emitted {
+            self.tokenize_until_a_token_is_emitted();
+        }
+        let token = self.tokens.pop_front()?;
+        if let HTMLToken::EndOfFile = token {
+            self.end_of_file_emitted = true;
+        }
+        Some(token)
+    }
+
+    fn tokenize_until_a_token_is_emitted(&mut self) {
         loop {
-            // print the tokens
-            // println!("tokens: {:?}", self.tokens);
-
-            // ... iterating html string
-            // checking for eof
-            // println!("char:{:?}", self.current_input_character);
-            // if self.current_input_character == None && self.next_input_character == None {
-            //     break;
-            // }
-            // self.consume_next_input_character();
-            // continue;
-            // ...
+            // some states emit more than one token at a time, so this is checked before anything
+            // else gets consumed
+            if !self.tokens.is_empty() {
+                break;
+            }
 
             match self.state {
                 // Data state
                 HTMLTokenizerState::Data => {
+                    if let Some(run) = self.plain_run(b'<', b'&', b'\r') {
+                        self.emit_character_run(run);
+                        continue;
+                    }
                     if let Some(current_input_character) = self.consume_next_input_character() {
                         match current_input_character {
-                            '&' => {}
+                            '&' => {
+                                self.return_state = Some(HTMLTokenizerState::Data);
+                                self.switch_state(HTMLTokenizerState::CharacterReference);
+                            }
                             '<' => {
                                 self.switch_state(HTMLTokenizerState::TagOpen);
                             }
-                            '\0' => {}
+                            '\0' => {
+                                // emit the null character as it is
+                                self.emit_parse_error(ParseError::UnexpectedNullCharacter);
+                                self.emit_character_token(current_input_character);
+                            }
                             _ => {
                                 // emit current_input_character as a character token
-                                let character_token = Character {
-                                    data: current_input_character.clone().to_string(),
-                                };
-                                self.emit_token(HTMLToken::Character(character_token));
+                                self.emit_character_token(current_input_character);
                             }
                         }
                     } else {
-                        eof_reached!("end of file reached");
+                        eof_reached!(self);
                     }
                 }
 
-                // Tag Open state
-                HTMLTokenizerState::TagOpen => {
+                // RCDATA state
+                HTMLTokenizerState::RCDATA => {
                     if let Some(current_input_character) = self.consume_next_input_character() {
                         match current_input_character {
-                            '!' => self.switch_state(HTMLTokenizerState::MarkupDeclarationOpen),
-                            '/' => self.switch_state(HTMLTokenizerState::EndTagOpen),
-                            'a'..='z' | 'A'..='Z' => {
-                                // create a start tag token
-                                self.current_tag_token = Some(Tag::StartTag {
-                                    tag_name: "".to_string(),
-                                    self_closing: false,
-                                    attributes: Vec::new()
-                                });
-                                // reconsume the current_input_character in the Tag Name state
-                                self.switch_state(HTMLTokenizerState::TagName);
-                                self.reconsume = true;
+                            '&' => {
+                                self.return_state = Some(HTMLTokenizerState::RCDATA);
+                                self.switch_state(HTMLTokenizerState::CharacterReference);
+                            }
+                            '<' => {
+                                self.switch_state(HTMLTokenizerState::RCDATALessThanSign);
+                            }
+                            '\0' => {
+                                self.emit_parse_error(ParseError::UnexpectedNullCharacter);
+                                self.emit_character_token(char::REPLACEMENT_CHARACTER);
                             }
                             _ => {
-                                // just ignore it
+                                self.emit_character_token(current_input_character);
                             }
                         }
                     } else {
-                        eof_reached!("end of file reached");
+                        eof_reached!(self);
                     }
                 }
 
-                // End Tag Open state
-                HTMLTokenizerState::EndTagOpen => {
+                // RAWTEXT state
+                HTMLTokenizerState::RAWTEXT => {
                     if let Some(current_input_character) = self.consume_next_input_character() {
                         match current_input_character {
-                            'a'..='z' | 'A'..='Z' => {
-                                // create a end tag token, set it's tag_name value to empty string
-                                self.current_tag_token = Some(Tag::EndTag {
-                                    tag_name: "".to_string(),
-                                    self_closing: false,
-                                    attributes: Vec::new()
-                                });
-
-                                // reconsume current_input_character in the tag name state
-                                self.switch_state(HTMLTokenizerState::TagName);
-                                self.reconsume = true;
+                            '<' => {
+                                self.switch_state(HTMLTokenizerState::RAWTEXTLessThanSign);
+                            }
+                            '\0' => {
+                                self.emit_parse_error(ParseError::UnexpectedNullCharacter);
+                                self.emit_character_token(char::REPLACEMENT_CHARACTER);
                             }
                             _ => {
-                                // ignore for now
+                                self.emit_character_token(current_input_character);
                             }
                         }
+                    } else {
+                        eof_reached!(self);
+                    }
+                }
+
+                // RCDATA Less-Than Sign state
+                HTMLTokenizerState::RCDATALessThanSign => {
+                    match self.consume_next_input_character() {
+                        Some('/') => {
+                            self.temporary_buffer = String::new();
+                            self.switch_state(HTMLTokenizerState::RCDATAEndTagOpen);
+                        }
+                        _ => {
+                            self.emit_character_token('<');
+                            self.switch_state(HTMLTokenizerState::RCDATA);
+                            self.reconsume = true;
+                        }
                     }
                 }
 
-                // Tag Name state
-                HTMLTokenizerState::TagName => {
-                    if let Some(current_input_character) =
-                        self.consume_next_input_character().clone()
-                    {
+                // RCDATA End Tag Open state
+                HTMLTokenizerState::RCDATAEndTagOpen => {
+                    self.consume_next_input_character();
+                    self.end_tag_open_state(
+                        HTMLTokenizerState::RCDATAEndTagName,
+                        HTMLTokenizerState::RCDATA,
+                    );
+                }
+
+                // RCDATA End Tag Name state
+                HTMLTokenizerState::RCDATAEndTagName => {
+                    self.consume_next_input_character();
+                    self.end_tag_name_state(HTMLTokenizerState::RCDATA);
+                }
+
+                // RAWTEXT Less-Than Sign state
+                HTMLTokenizerState::RAWTEXTLessThanSign => {
+                    match self.consume_next_input_character() {
+                        Some('/') => {
+                            self.temporary_buffer = String::new();
+                            self.switch_state(HTMLTokenizerState::RAWTEXTEndTagOpen);
+                        }
+                        _ => {
+                            self.emit_character_token('<');
+                            self.switch_state(HTMLTokenizerState::RAWTEXT);
+                            self.reconsume = true;
+                        }
+                    }
+                }
+
+                // RAWTEXT End Tag Open state
+                HTMLTokenizerState::RAWTEXTEndTagOpen => {
+                    self.consume_next_input_character();
+                    self.end_tag_open_state(
+                        HTMLTokenizerState::RAWTEXTEndTagName,
+                        HTMLTokenizerState::RAWTEXT,
+                    );
+                }
+
+                // RAWTEXT End Tag Name state
+                HTMLTokenizerState::RAWTEXTEndTagName => {
+                    self.consume_next_input_character();
+                    self.end_tag_name_state(HTMLTokenizerState::RAWTEXT);
+                }
+
+                // Script Data state
+                HTMLTokenizerState::ScriptData => {
+                    if let Some(current_input_character) = self.consume_next_input_character() {
                         match current_input_character {
-                            '\t' | '\n' | '\x0C' | ' ' => {
-                                self.switch_state(HTMLTokenizerState::BeforeAttributeName);
-                            }
-                            '/' => {
-                                // ignore for now
-                            }
-                            '>' => {
-                                // switch to data state
-                                self.switch_state(HTMLTokenizerState::Data);
-                                //emit current tag token.
-                                self.emit_current_tag_token();
-                            }
-                            'A'..='Z' => {
-                                // ignore for now
+                            '<' => {
+                                self.switch_state(HTMLTokenizerState::ScriptDataLessThanSign);
                             }
                             '\0' => {
-                                // ignore for now
+                                self.emit_parse_error(ParseError::UnexpectedNullCharacter);
+                                self.emit_character_token(char::REPLACEMENT_CHARACTER);
                             }
                             _ => {
-                                // append current_input_character to current tag token's name
-                                if let Some(tag_token) = &mut self.current_tag_token {
-                                    // append current_input_character to start tag's name
-                                    match tag_token {
-                                        Tag::StartTag {
-                                            tag_name,
-                                            self_closing: _,
-                                            attributes: _,
-                                        }
-                                        | Tag::EndTag {
-                                            tag_name,
-                                            self_closing: _,
-                                            attributes: _,
-                                        } => {
-                                            tag_name.push(current_input_character);
-                                        }
-                                    }
-                                }
+                                self.emit_character_token(current_input_character);
                             }
                         }
                     } else {
-                        eof_reached!("end of file reached");
+                        eof_reached!(self);
                     }
                 }
 
-                // Before Attribute Name state
-                HTMLTokenizerState::BeforeAttributeName => {
-                    if let Some(current_input_character) =
-                        self.consume_next_input_character().clone()
-                    {
+                // PLAINTEXT state
+                HTMLTokenizerState::PLAINTEXT => {
+                    // there's no way out of this state, everything up to the end is text
+                    if let Some(current_input_character) = self.consume_next_input_character() {
                         match current_input_character {
-                            '\t' | '\n' | '\x0C' | ' ' => {
-                                // ignore the character
-                                continue;
-                            }
-                            '/' | '>'   // also matches for eof
-                            => {
-                                self.reconsume = true;
-                                self.switch_state(HTMLTokenizerState::AfterAttributeName);
+                            '\0' => {
+                                self.emit_parse_error(ParseError::UnexpectedNullCharacter);
+                                self.emit_character_token(char::REPLACEMENT_CHARACTER);
                             }
-                            '=' => {}
                             _ => {
-                                // start a new attribute in the current tag token. Set that
-                                // attribute name and value to empty string.
-                                if let Some(tag_token) = &mut self.current_tag_token {
-                                    match tag_token {
-                                        Tag::StartTag {
-                                            tag_name: _,
-                                            self_closing: _,
-                                            attributes,
-                                        }
-                                        | Tag::EndTag {
-                                            tag_name: _,
-                                            self_closing: _,
-                                            attributes,
-                                        } => {
-                                            // push a new attribute to the attributes vector
-                                            let new_attribute = Attribute{key: "".to_string(), value: "".to_string()};
-                                            attributes.push(new_attribute);
-                                            // switch to Attribute name state and reconsume the
-                                            // current_input_character
-                                            self.switch_state(HTMLTokenizerState::AttributeName);
-                                            self.reconsume = true;
-                                        }
-                                    }
-                                }
+                                self.emit_character_token(current_input_character);
                             }
                         }
                     } else {
-                        eof_reached!("end of file reached");
+                        eof_reached!(self);
                     }
                 }
 
-                // After Attribute Name state
-                HTMLTokenizerState::AfterAttributeName => {
-                    if let Some(current_input_character) = self.consume_next_input_character(){
-                        match current_input_character{
-                            '\t' | '\n' | '\x0C' | ' ' => {
-                                continue;
-                            }
-                            '/' => {
-                                self.switch_state(HTMLTokenizerState::SelfClosingStartTag);
-                            }
-                            '=' => {}
-                            '>' => {}
-                            _ => {}
+                // Script Data Less-Than Sign state
+                HTMLTokenizerState::ScriptDataLessThanSign => {
+                    match self.consume_next_input_character() {
+                        Some('/') => {
+                            self.temporary_buffer = String::new();
+                            self.switch_state(HTMLTokenizerState::ScriptDataEndTagOpen);
+                        }
+                        Some('!') => {
+                            self.switch_state(HTMLTokenizerState::ScriptDataEscapeStart);
+                            self.emit_character_token('<');
+                            self.emit_character_token('!');
+                        }
+                        _ => {
+                            self.emit_character_token('<');
+                            self.switch_state(HTMLTokenizerState::ScriptData);
+                            self.reconsume = true;
                         }
                     }
                 }
 
+                // Script Data End Tag Open state
+                HTMLTokenizerState::ScriptDataEndTagOpen => {
+                    self.consume_next_input_character();
+                    self.end_tag_open_state(
+                        HTMLTokenizerState::ScriptDataEndTagName,
+                        HTMLTokenizerState::ScriptData,
+                    );
+                }
 
-                // Self-Closing Start Tag state
-                HTMLTokenizerState::SelfClosingStartTag => {
-                    if let Some(current_input_character) = self.consume_next_input_character() {
-                        match current_input_character{
-                            '>' => {
-                                // set the self-closing tag of the current tag token
-                                if let Some(tag_token) = &mut self.current_tag_token{
-                                    match tag_token{
-                                        Tag::StartTag { tag_name: _, self_closing, attributes: _ } 
-                                        | 
-                                        Tag::EndTag { tag_name: _, self_closing, attributes: _ } => {
-                                            *self_closing = true;
-                                            self.switch_state(HTMLTokenizerState::Data);
-                                            self.emit_current_tag_token();
-                                        }
-                                    }
-                                }
-                            }
-                            _ => { }
+                // Script Data End Tag Name state
+                HTMLTokenizerState::ScriptDataEndTagName => {
+                    self.consume_next_input_character();
+                    self.end_tag_name_state(HTMLTokenizerState::ScriptData);
+                }
+
+                // Script Data Escape Start state
+                HTMLTokenizerState::ScriptDataEscapeStart => {
+                    match self.consume_next_input_character() {
+                        Some('-') => {
+                            self.switch_state(HTMLTokenizerState::ScriptDataEscapeStartDash);
+                            self.emit_character_token('-');
+                        }
+                        _ => {
+                            self.switch_state(HTMLTokenizerState::ScriptData);
+                            self.reconsume = true;
                         }
                     }
                 }
 
-                // Attribute Name state
-                HTMLTokenizerState::AttributeName => {
-                    if let Some(current_input_character) = self.consume_next_input_character().clone() {
+                // Script Data Escape Start Dash state
+                HTMLTokenizerState::ScriptDataEscapeStartDash => {
+                    match self.consume_next_input_character() {
+                        Some('-') => {
+                            self.switch_state(HTMLTokenizerState::ScriptDataEscapedDashDash);
+                            self.emit_character_token('-');
+                        }
+                        _ => {
+                            self.switch_state(HTMLTokenizerState::ScriptData);
+                            self.reconsume = true;
+                        }
+                    }
+                }
+
+                // Script Data Escaped state
+                HTMLTokenizerState::ScriptDataEscaped => {
+                    if let Some(current_input_character) = self.consume_next_input_character() {
                         match current_input_character {
-                            '\t' | '\n' | '\x0C' | ' ' | '/' | '>'   // also includes an eof
-                            => {
-                                // ignore for now
+                            '-' => {
+                                self.switch_state(HTMLTokenizerState::ScriptDataEscapedDash);
+                                self.emit_character_token('-');
                             }
-                            '=' => {
-                                self.switch_state(HTMLTokenizerState::BeforeAttributeValue);
+                            '<' => {
+                                self.switch_state(HTMLTokenizerState::ScriptDataEscapedLessThanSign);
+                            }
+                            '\0' => {
+                                self.emit_parse_error(ParseError::UnexpectedNullCharacter);
+                                self.emit_character_token(char::REPLACEMENT_CHARACTER);
                             }
-                            'A'..='Z' => {}
-                            '\0' => {}
-                            '"' | '\'' | '<' => {}
                             _ => {
-                                // append current_input_character to current attribute's name
-                                if let Some(tag_token) = &mut self.current_tag_token {
-                                    match tag_token{
-                                        Tag::StartTag { tag_name: _, self_closing: _, attributes } 
-                                        | 
-                                        Tag::EndTag { tag_name: _, self_closing: _, attributes } => {
-                                            // the `current attribute` is the last attribute in the
-                                            // attributes vector
-                                            if let Some(current_attribute) = attributes.last_mut(){
-                                                current_attribute.key.push(current_input_character);
-                                            }
-                                        }
-                                    }
-                                }
+                                self.emit_character_token(current_input_character);
                             }
                         }
+                    } else {
+                        self.emit_parse_error(ParseError::EofInScriptHtmlCommentLikeText);
+                        eof_reached!(self);
                     }
                 }
 
-
-                // Before Attribute Value state
-                HTMLTokenizerState::BeforeAttributeValue => {
+                // Script Data Escaped Dash state
+                HTMLTokenizerState::ScriptDataEscapedDash => {
                     if let Some(current_input_character) = self.consume_next_input_character() {
                         match current_input_character {
-                            '\t' | '\n' | '\x0C' | ' ' => {
-                                // ignore the character
-                                continue;
+                            '-' => {
+                                self.switch_state(HTMLTokenizerState::ScriptDataEscapedDashDash);
+                                self.emit_character_token('-');
                             }
-                            '"' => {
-                                self.switch_state(HTMLTokenizerState::AttributeValueDoubleQuoted);
+                            '<' => {
+                                self.switch_state(HTMLTokenizerState::ScriptDataEscapedLessThanSign);
                             }
-                            '\'' => {
-                                self.switch_state(HTMLTokenizerState::AttributeValueSingleQuoted);
+                            '\0' => {
+                                self.emit_parse_error(ParseError::UnexpectedNullCharacter);
+                                self.switch_state(HTMLTokenizerState::ScriptDataEscaped);
+                                self.emit_character_token(char::REPLACEMENT_CHARACTER);
                             }
-                            '>' => {}
                             _ => {
-                                // ignore for now
+                                self.switch_state(HTMLTokenizerState::ScriptDataEscaped);
+                                self.emit_character_token(current_input_character);
                             }
                         }
+                    } else {
+                        self.emit_parse_error(ParseError::EofInScriptHtmlCommentLikeText);
+                        eof_reached!(self);
                     }
                 }
 
-                // Attribute Value Single Quoted state
-                HTMLTokenizerState::AttributeValueSingleQuoted => {
+                // Script Data Escaped Dash Dash state
+                HTMLTokenizerState::ScriptDataEscapedDashDash => {
                     if let Some(current_input_character) = self.consume_next_input_character() {
                         match current_input_character {
-                            '\'' => self.switch_state(HTMLTokenizerState::AfterAttributeValueQuoted),
-                            '&' => {}
-                            '\0' => {}
-                            _=> {
-                                self.append_current_input_character_to_current_attribute_value();
+                            '-' => {
+                                self.emit_character_token('-');
+                            }
+                            '<' => {
+                                self.switch_state(HTMLTokenizerState::ScriptDataEscapedLessThanSign);
+                            }
+                            '>' => {
+                                self.switch_state(HTMLTokenizerState::ScriptData);
+                                self.emit_character_token('>');
+                            }
+                            '\0' => {
+                                self.emit_parse_error(ParseError::UnexpectedNullCharacter);
+                                self.switch_state(HTMLTokenizerState::ScriptDataEscaped);
+                                self.emit_character_token(char::REPLACEMENT_CHARACTER);
+                            }
+                            _ => {
+                                self.switch_state(HTMLTokenizerState::ScriptDataEscaped);
+                                self.emit_character_token(current_input_character);
                             }
                         }
+                    } else {
+                        self.emit_parse_error(ParseError::EofInScriptHtmlCommentLikeText);
+                        eof_reached!(self);
+                    }
+                }
+
+                // Script Data Escaped Less-Than Sign state
+                HTMLTokenizerState::ScriptDataEscapedLessThanSign => {
+                    match self.consume_next_input_character() {
+                        Some('/') => {
+                            self.temporary_buffer = String::new();
+                            self.switch_state(HTMLTokenizerState::ScriptDataEscapedEndTagOpen);
+                        }
+                        Some('a'..='z' | 'A'..='Z') => {
+                            self.temporary_buffer = String::new();
+                            self.emit_character_token('<');
+                            self.switch_state(HTMLTokenizerState::ScriptDataDoubleEscapeStart);
+                            self.reconsume = true;
+                        }
+                        _ => {
+                            self.emit_character_token('<');
+                            self.switch_state(HTMLTokenizerState::ScriptDataEscaped);
+                            self.reconsume = true;
+                        }
                     }
                 }
 
+                // Script Data Escaped End Tag Open state
+                HTMLTokenizerState::ScriptDataEscapedEndTagOpen => {
+                    self.consume_next_input_character();
+                    self.end_tag_open_state(
+                        HTMLTokenizerState::ScriptDataEscapedEndTagName,
+                        HTMLTokenizerState::ScriptDataEscaped,
+                    );
+                }
 
-                // Attribute Value Double Quoted state
-                HTMLTokenizerState::AttributeValueDoubleQuoted => {
-                    if let Some(current_input_character) = self.consume_next_input_character().clone() {
+                // Script Data Escaped End Tag Name state
+                HTMLTokenizerState::ScriptDataEscapedEndTagName => {
+                    self.consume_next_input_character();
+                    self.end_tag_name_state(HTMLTokenizerState::ScriptDataEscaped);
+                }
+
+                // Script Data Double Escape Start state
+                HTMLTokenizerState::ScriptDataDoubleEscapeStart => {
+                    self.consume_next_input_character();
+                    self.double_escape_boundary_state(
+                        HTMLTokenizerState::ScriptDataDoubleEscaped,
+                        HTMLTokenizerState::ScriptDataEscaped,
+                    );
+                }
+
+                // Script Data Double Escaped state
+                HTMLTokenizerState::ScriptDataDoubleEscaped => {
+                    if let Some(current_input_character) = self.consume_next_input_character() {
                         match current_input_character {
-                            '"' => {
-                                self.switch_state(HTMLTokenizerState::AfterAttributeValueQuoted);
+                            '-' => {
+                                self.switch_state(HTMLTokenizerState::ScriptDataDoubleEscapedDash);
+                                self.emit_character_token('-');
                             }
-                            '&' => {}
-                            '\0' => {}
-                            _=> {
-                                self.append_current_input_character_to_current_attribute_value();
+                            '<' => {
+                                self.switch_state(HTMLTokenizerState::ScriptDataDoubleEscapedLessThanSign);
+                                self.emit_character_token('<');
+                            }
+                            '\0' => {
+                                self.emit_parse_error(ParseError::UnexpectedNullCharacter);
+                                self.emit_character_token(char::REPLACEMENT_CHARACTER);
+                            }
+                            _ => {
+                                self.emit_character_token(current_input_character);
                             }
                         }
+                    } else {
+                        self.emit_parse_error(ParseError::EofInScriptHtmlCommentLikeText);
+                        eof_reached!(self);
                     }
                 }
 
-                // After Attribute Value Quoted state
-                HTMLTokenizerState::AfterAttributeValueQuoted => {
-                    if let Some(current_input_character) = self.consume_next_input_character(){
-                        match current_input_character{
-                            '\t' | '\n' | '\x0C' | ' ' => {
-                                self.switch_state(HTMLTokenizerState::BeforeAttributeName);
+                // Script Data Double Escaped Dash state
+                HTMLTokenizerState::ScriptDataDoubleEscapedDash => {
+                    if let Some(current_input_character) = self.consume_next_input_character() {
+                        match current_input_character {
+                            '-' => {
+                                self.switch_state(HTMLTokenizerState::ScriptDataDoubleEscapedDashDash);
+                                self.emit_character_token('-');
                             }
-                            '/' => {
-                                // ignore for now
+                            '<' => {
+                                self.switch_state(HTMLTokenizerState::ScriptDataDoubleEscapedLessThanSign);
+                                self.emit_character_token('<');
+                            }
+                            '\0' => {
+                                self.emit_parse_error(ParseError::UnexpectedNullCharacter);
+                                self.switch_state(HTMLTokenizerState::ScriptDataDoubleEscaped);
+                                self.emit_character_token(char::REPLACEMENT_CHARACTER);
+                            }
+                            _ => {
+                                self.switch_state(HTMLTokenizerState::ScriptDataDoubleEscaped);
+                                self.emit_character_token(current_input_character);
+                            }
+                        }
+                    } else {
+                        self.emit_parse_error(ParseError::EofInScriptHtmlCommentLikeText);
+                        eof_reached!(self);
+                    }
+                }
+
+                // Script Data Double Escaped Dash Dash state
+                HTMLTokenizerState::ScriptDataDoubleEscapedDashDash => {
+                    if let Some(current_input_character) = self.consume_next_input_character() {
+                        match current_input_character {
+                            '-' => {
+                                self.emit_character_token('-');
+                            }
+                            '<' => {
+                                self.switch_state(HTMLTokenizerState::ScriptDataDoubleEscapedLessThanSign);
+                                self.emit_character_token('<');
                             }
                             '>' => {
-                                // emit current_tag_token
+                                self.switch_state(HTMLTokenizerState::ScriptData);
+                                self.emit_character_token('>');
+                            }
+                            '\0' => {
+                                self.emit_parse_error(ParseError::UnexpectedNullCharacter);
+                                self.switch_state(HTMLTokenizerState::ScriptDataDoubleEscaped);
+                                self.emit_character_token(char::REPLACEMENT_CHARACTER);
+                            }
+                            _ => {
+                                self.switch_state(HTMLTokenizerState::ScriptDataDoubleEscaped);
+                                self.emit_character_token(current_input_character);
+                            }
+                        }
+                    } else {
+                        self.emit_parse_error(ParseError::EofInScriptHtmlCommentLikeText);
+                        eof_reached!(self);
+                    }
+                }
+
+                // Script Data Double Escaped Less-Than Sign state
+                HTMLTokenizerState::ScriptDataDoubleEscapedLessThanSign => {
+                    match self.consume_next_input_character() {
+                        Some('/') => {
+                            self.temporary_buffer = String::new();
+                            self.switch_state(HTMLTokenizerState::ScriptDataDoubleEscapeEnd);
+                            self.emit_character_token('/');
+                        }
+                        _ => {
+                            self.switch_state(HTMLTokenizerState::ScriptDataDoubleEscaped);
+                            self.reconsume = true;
+                        }
+                    }
+                }
+
+                // Script Data Double Escape End state
+                HTMLTokenizerState::ScriptDataDoubleEscapeEnd => {
+                    self.consume_next_input_character();
+                    self.double_escape_boundary_state(
+                        HTMLTokenizerState::ScriptDataEscaped,
+                        HTMLTokenizerState::ScriptDataDoubleEscaped,
+                    );
+                }
+
+                // Tag Open state
+                HTMLTokenizerState::TagOpen => {
+                    if let Some(current_input_character) = self.consume_next_input_character() {
+                        match current_input_character {
+                            '!' => self.switch_state(HTMLTokenizerState::MarkupDeclarationOpen),
+                            '/' => self.switch_state(HTMLTokenizerState::EndTagOpen),
+                            'a'..='z' | 'A'..='Z' => {
+                                // create a start tag token
+                                self.current_tag_token = Some(Tag::StartTag {
+                                    tag_name: Cow::Borrowed(""),
+                                    self_closing: false,
+                                    attributes: Vec::new(),
+                                    original_tag_name: None,
+                                    span: SourceSpan::default(),
+                                });
+                                // reconsume the current_input_character in the Tag Name state
+                                self.switch_state(HTMLTokenizerState::TagName);
+                                self.reconsume = true;
+                            }
+                            '?' => {
+                                // processing instructions like `<?xml ...?>` become comments
+                                self.emit_parse_error(ParseError::UnexpectedQuestionMarkInsteadOfTagName);
+                                self.current_comment_token = Some(Comment {
+                                    data: Cow::Borrowed(""),
+                                    span: SourceSpan::default(),
+                                });
+                                self.switch_state(HTMLTokenizerState::BogusComment);
+                                self.reconsume = true;
+                            }
+                            _ => {
+                                // the `<` wasn't the start of a tag, so it's just text
+                                self.emit_parse_error(ParseError::InvalidFirstCharacterOfTagName);
+                                self.emit_character_token('<');
+                                self.switch_state(HTMLTokenizerState::Data);
+                                self.reconsume = true;
+                            }
+                        }
+                    } else {
+                        self.emit_parse_error(ParseError::EofBeforeTagName);
+                        self.emit_character_token('<');
+                        eof_reached!(self);
+                    }
+                }
+
+                // End Tag Open state
+                HTMLTokenizerState::EndTagOpen => {
+                    match self.consume_next_input_character() {
+                        Some('a'..='z' | 'A'..='Z') => {
+                            // create a end tag token, set it's tag_name value to empty string
+                            self.current_tag_token = Some(Tag::EndTag {
+                                tag_name: Cow::Borrowed(""),
+                                self_closing: false,
+                                attributes: Vec::new(),
+                                original_tag_name: None,
+                                span: SourceSpan::default(),
+                            });
+
+                            // reconsume current_input_character in the tag name state
+                            self.switch_state(HTMLTokenizerState::TagName);
+                            self.reconsume = true;
+                        }
+                        Some('>') => {
+                            // `</>` is dropped altogether
+                            self.emit_parse_error(ParseError::MissingEndTagName);
+                            self.switch_state(HTMLTokenizerState::Data);
+                        }
+                        Some(_) => {
+                            self.emit_parse_error(ParseError::InvalidFirstCharacterOfTagName);
+                            self.current_comment_token = Some(Comment {
+                                data: Cow::Borrowed(""),
+                                span: SourceSpan::default(),
+                            });
+                            self.switch_state(HTMLTokenizerState::BogusComment);
+                            self.reconsume = true;
+                        }
+                        None => {
+                            self.emit_parse_error(ParseError::EofBeforeTagName);
+                            self.emit_character_token('<');
+                            self.emit_character_token('/');
+                            eof_reached!(self);
+                        }
+                    }
+                }
+
+                // Tag Name state
+                HTMLTokenizerState::TagName => {
+                    if let Some(current_input_character) =
+                        self.consume_next_input_character()
+                    {
+                        match current_input_character {
+                            '\t' | '\n' | '\x0C' | ' ' => {
+                                self.switch_state(HTMLTokenizerState::BeforeAttributeName);
+                            }
+                            '/' => {
+                                self.switch_state(HTMLTokenizerState::SelfClosingStartTag);
+                            }
+                            '>' => {
+                                // switch to data state
+                                self.switch_state(HTMLTokenizerState::Data);
+                                //emit current tag token.
+                                self.emit_current_tag_token();
+                            }
+                            '\0' => {
+                                self.emit_parse_error(ParseError::UnexpectedNullCharacter);
+                                self.append_to_current_tag_name(char::REPLACEMENT_CHARACTER);
+                            }
+                            _ => {
+                                // uppercase ASCII letters are appended in lowercase
+                                self.append_to_current_tag_name(current_input_character);
+                            }
+                        }
+                    } else {
+                        // the unfinished tag is dropped
+                        self.emit_parse_error(ParseError::EofInTag);
+                        eof_reached!(self);
+                    }
+                }
+
+                // Before Attribute Name state
+                HTMLTokenizerState::BeforeAttributeName => {
+                    match self.consume_next_input_character() {
+                        Some('\t' | '\n' | '\x0C' | ' ') => {
+                            // ignore the character
+                            continue;
+                        }
+                        Some('/' | '>') | None => {
+                            self.switch_state(HTMLTokenizerState::AfterAttributeName);
+                            self.reconsume = true;
+                        }
+                        Some('=') => {
+                            // the `=` becomes the first character of the attribute's name
+                            self.emit_parse_error(ParseError::UnexpectedEqualsSignBeforeAttributeName);
+                            self.start_new_attribute();
+                            self.append_to_current_attribute_name('=');
+                            self.switch_state(HTMLTokenizerState::AttributeName);
+                        }
+                        Some(_) => {
+                            // start a new attribute in the current tag token, with its name and
+                            // value set to the empty string, and reconsume in the attribute name
+                            // state
+                            self.start_new_attribute();
+                            self.switch_state(HTMLTokenizerState::AttributeName);
+                            self.reconsume = true;
+                        }
+                    }
+                }
+
+                // After Attribute Name state
+                HTMLTokenizerState::AfterAttributeName => {
+                    if let Some(current_input_character) = self.consume_next_input_character() {
+                        match current_input_character {
+                            '\t' | '\n' | '\x0C' | ' ' => {
+                                // ignore the character
+                                continue;
+                            }
+                            '/' => {
+                                self.switch_state(HTMLTokenizerState::SelfClosingStartTag);
+                            }
+                            '=' => {
+                                self.switch_state(HTMLTokenizerState::BeforeAttributeValue);
+                            }
+                            '>' => {
+                                self.switch_state(HTMLTokenizerState::Data);
+                                self.emit_current_tag_token();
+                            }
+                            _ => {
+                                self.start_new_attribute();
+                                self.switch_state(HTMLTokenizerState::AttributeName);
+                                self.reconsume = true;
+                            }
+                        }
+                    } else {
+                        self.emit_parse_error(ParseError::EofInTag);
+                        eof_reached!(self);
+                    }
+                }
+
+                // Self-Closing Start Tag state
+                HTMLTokenizerState::SelfClosingStartTag => {
+                    match self.consume_next_input_character() {
+                        Some('>') => {
+                            // set the self-closing flag of the current tag token
+                            if let Some(
+                                Tag::StartTag { self_closing, .. } | Tag::EndTag { self_closing, .. },
+                            ) = &mut self.current_tag_token
+                            {
+                                *self_closing = true;
+                            }
+                            self.switch_state(HTMLTokenizerState::Data);
+                            self.emit_current_tag_token();
+                        }
+                        Some(_) => {
+                            self.emit_parse_error(ParseError::UnexpectedSolidusInTag);
+                            self.switch_state(HTMLTokenizerState::BeforeAttributeName);
+                            self.reconsume = true;
+                        }
+                        None => {
+                            self.emit_parse_error(ParseError::EofInTag);
+                            eof_reached!(self);
+                        }
+                    }
+                }
+
+                // Attribute Name state
+                HTMLTokenizerState::AttributeName => {
+                    match self.consume_next_input_character() {
+                        Some('\t' | '\n' | '\x0C' | ' ' | '/' | '>') | None => {
+                            self.end_current_attribute_name();
+                            self.check_for_duplicate_attribute();
+                            self.switch_state(HTMLTokenizerState::AfterAttributeName);
+                            self.reconsume = true;
+                        }
+                        Some('=') => {
+                            self.end_current_attribute_name();
+                            self.check_for_duplicate_attribute();
+                            self.switch_state(HTMLTokenizerState::BeforeAttributeValue);
+                        }
+                        Some('\0') => {
+                            self.emit_parse_error(ParseError::UnexpectedNullCharacter);
+                            self.append_to_current_attribute_name(char::REPLACEMENT_CHARACTER);
+                        }
+                        Some(current_input_character) => {
+                            if let '"' | '\'' | '<' = current_input_character {
+                                // still appended to the name like any other character
+                                self.emit_parse_error(ParseError::UnexpectedCharacterInAttributeName);
+                            }
+                            // uppercase ASCII letters are appended in lowercase
+                            self.append_to_current_attribute_name(current_input_character);
+                        }
+                    }
+                }
+
+                // Before Attribute Value state
+                HTMLTokenizerState::BeforeAttributeValue => {
+                    match self.consume_next_input_character() {
+                        Some('\t' | '\n' | '\x0C' | ' ') => {
+                            // ignore the character
+                            continue;
+                        }
+                        Some('"') => {
+                            // the value starts after the quote
+                            self.start_current_attribute_value(self.next_position);
+                            self.switch_state(HTMLTokenizerState::AttributeValueDoubleQuoted);
+                        }
+                        Some('\'') => {
+                            self.start_current_attribute_value(self.next_position);
+                            self.switch_state(HTMLTokenizerState::AttributeValueSingleQuoted);
+                        }
+                        Some('>') => {
+                            self.emit_parse_error(ParseError::MissingAttributeValue);
+                            self.switch_state(HTMLTokenizerState::Data);
+                            self.emit_current_tag_token();
+                        }
+                        _ => {
+                            // this also covers eof
+                            self.start_current_attribute_value(self.current_position);
+                            self.switch_state(HTMLTokenizerState::AttributeValueUnquoted);
+                            self.reconsume = true;
+                        }
+                    }
+                }
+
+                // Attribute Value Single Quoted state
+                HTMLTokenizerState::AttributeValueSingleQuoted => {
+                    if let Some(run) = self.plain_run(b'\'', b'&', b'\r') {
+                        self.append_run_to_current_attribute_value(run);
+                        continue;
+                    }
+                    if let Some(current_input_character) = self.consume_next_input_character() {
+                        match current_input_character {
+                            '\'' => {
+                                self.end_current_attribute_value();
+                                self.switch_state(HTMLTokenizerState::AfterAttributeValueQuoted);
+                            }
+                            '&' => {
+                                self.return_state = Some(HTMLTokenizerState::AttributeValueSingleQuoted);
+                                self.switch_state(HTMLTokenizerState::CharacterReference);
+                            }
+                            '\0' => {
+                                self.emit_parse_error(ParseError::UnexpectedNullCharacter);
+                                self.append_to_current_attribute_value("\u{FFFD}");
+                            }
+                            _=> {
+                                self.append_current_input_character_to_current_attribute_value();
+                            }
+                        }
+                    } else {
+                        self.emit_parse_error(ParseError::EofInTag);
+                        eof_reached!(self);
+                    }
+                }
+
+
+                // Attribute Value Double Quoted state
+                HTMLTokenizerState::AttributeValueDoubleQuoted => {
+                    if let Some(run) = self.plain_run(b'"', b'&', b'\r') {
+                        self.append_run_to_current_attribute_value(run);
+                        continue;
+                    }
+                    if let Some(current_input_character) = self.consume_next_input_character() {
+                        match current_input_character {
+                            '"' => {
+                                self.end_current_attribute_value();
+                                self.switch_state(HTMLTokenizerState::AfterAttributeValueQuoted);
+                            }
+                            '&' => {
+                                self.return_state = Some(HTMLTokenizerState::AttributeValueDoubleQuoted);
+                                self.switch_state(HTMLTokenizerState::CharacterReference);
+                            }
+                            '\0' => {
+                                self.emit_parse_error(ParseError::UnexpectedNullCharacter);
+                                self.append_to_current_attribute_value("\u{FFFD}");
+                            }
+                            _=> {
+                                self.append_current_input_character_to_current_attribute_value();
+                            }
+                        }
+                    } else {
+                        self.emit_parse_error(ParseError::EofInTag);
+                        eof_reached!(self);
+                    }
+                }
+
+                // Attribute Value Unquoted state
+                HTMLTokenizerState::AttributeValueUnquoted => {
+                    if let Some(current_input_character) = self.consume_next_input_character() {
+                        match current_input_character {
+                            '\t' | '\n' | '\x0C' | ' ' => {
+                                self.end_current_attribute_value();
+                                self.switch_state(HTMLTokenizerState::BeforeAttributeName);
+                            }
+                            '&' => {
+                                self.return_state = Some(HTMLTokenizerState::AttributeValueUnquoted);
+                                self.switch_state(HTMLTokenizerState::CharacterReference);
+                            }
+                            '>' => {
+                                self.end_current_attribute_value();
+                                self.switch_state(HTMLTokenizerState::Data);
                                 self.emit_current_tag_token();
+                            }
+                            '\0' => {
+                                self.emit_parse_error(ParseError::UnexpectedNullCharacter);
+                                self.append_to_current_attribute_value("\u{FFFD}");
+                            }
+                            _ => {
+                                if let '"' | '\'' | '<' | '=' | '`' = current_input_character {
+                                    // still appended to the value like any other character
+                                    self.emit_parse_error(
+                                        ParseError::UnexpectedCharacterInUnquotedAttributeValue,
+                                    );
+                                }
+                                self.append_current_input_character_to_current_attribute_value();
+                            }
+                        }
+                    } else {
+                        self.emit_parse_error(ParseError::EofInTag);
+                        eof_reached!(self);
+                    }
+                }
+
+                // After Attribute Value Quoted state
+                HTMLTokenizerState::AfterAttributeValueQuoted => {
+                    if let Some(current_input_character) = self.consume_next_input_character(){
+                        match current_input_character{
+                            '\t' | '\n' | '\x0C' | ' ' => {
+                                self.switch_state(HTMLTokenizerState::BeforeAttributeName);
+                            }
+                            '/' => {
+                                self.switch_state(HTMLTokenizerState::SelfClosingStartTag);
+                            }
+                            '>' => {
                                 // switch to data state
                                 self.switch_state(HTMLTokenizerState::Data);
+                                // emit current_tag_token
+                                self.emit_current_tag_token();
                             }
                             _=> {
-                                // ignore for now
+                                self.emit_parse_error(ParseError::MissingWhitespaceBetweenAttributes);
+                                self.switch_state(HTMLTokenizerState::BeforeAttributeName);
+                                self.reconsume = true;
                             }
                         }
+                    } else {
+                        self.emit_parse_error(ParseError::EofInTag);
+                        eof_reached!(self);
                     }
                 }
 
                 // Markup Declaration Open state
                 HTMLTokenizerState::MarkupDeclarationOpen => {
-                    if self.next_few_characters_are("DOCTYPE".to_string()) {
-                        // consume/move cursor over "DOCTYPE"
-                        self.consume_substring("DOCTYPE".to_string());
-                        self.switch_state(HTMLTokenizerState::DOCTYPE);
-                    } else if self.next_few_characters_are("--".to_string()) {
+                    if self.next_few_characters_are("--".to_string()) {
                         // consume "--"
                         self.consume_substring("--".to_string());
                         self.current_comment_token = Some(Comment {
-                            data: "".to_string(),
+                            data: Cow::Borrowed(""),
+                            span: SourceSpan::default(),
                         });
                         self.switch_state(HTMLTokenizerState::CommentStart);
+                    } else if self.next_few_characters_are("DOCTYPE".to_string()) {
+                        // consume/move cursor over "DOCTYPE"
+                        self.consume_substring("DOCTYPE".to_string());
+                        self.switch_state(HTMLTokenizerState::DOCTYPE);
+                    } else if self.next_few_characters_are_exactly("[CDATA[") {
+                        self.consume_substring("[CDATA[".to_string());
+                        if self.in_foreign_content {
+                            self.switch_state(HTMLTokenizerState::CDATASection);
+                        } else {
+                            // CDATA sections only exist in svg and math content, in html they
+                            // are just bogus comments
+                            self.emit_parse_error(ParseError::CdataInHtmlContent);
+                            self.current_comment_token = Some(Comment {
+                                data: Cow::Borrowed("[CDATA["),
+                                span: SourceSpan::default(),
+                            });
+                            self.switch_state(HTMLTokenizerState::BogusComment);
+                        }
                     } else {
-                        // ignore it
+                        // nothing is consumed, what comes next becomes the comment's data
+                        self.emit_parse_error(ParseError::IncorrectlyOpenedComment);
+                        self.current_comment_token = Some(Comment {
+                            data: Cow::Borrowed(""),
+                            span: SourceSpan::default(),
+                        });
+                        self.switch_state(HTMLTokenizerState::BogusComment);
                     }
                 }
 
-                // Comment Start state
-                HTMLTokenizerState::CommentStart => {
-                    if let Some(current_input_character) =
-                        self.consume_next_input_character().clone()
-                    {
+                // Bogus Comment state
+                HTMLTokenizerState::BogusComment => {
+                    if let Some(current_input_character) = self.consume_next_input_character() {
                         match current_input_character {
-                            '-' => {}
-                            '>' => {}
+                            '>' => {
+                                self.switch_state(HTMLTokenizerState::Data);
+                                self.emit_current_comment_token();
+                            }
+                            '\0' => {
+                                self.emit_parse_error(ParseError::UnexpectedNullCharacter);
+                                self.append_to_current_comment(char::REPLACEMENT_CHARACTER);
+                            }
                             _ => {
-                                // reconsume in the comment state
-                                self.switch_state(HTMLTokenizerState::Comment);
-                                self.reconsume = true;
+                                self.append_to_current_comment(current_input_character);
                             }
                         }
                     } else {
-                        eof_reached!("end of file reached");
+                        self.emit_current_comment_token();
+                        eof_reached!(self);
+                    }
+                }
+
+                // Comment Start state
+                HTMLTokenizerState::CommentStart => {
+                    match self.consume_next_input_character() {
+                        Some('-') => {
+                            self.switch_state(HTMLTokenizerState::CommentStartDash);
+                        }
+                        Some('>') => {
+                            self.emit_parse_error(ParseError::AbruptClosingOfEmptyComment);
+                            self.switch_state(HTMLTokenizerState::Data);
+                            self.emit_current_comment_token();
+                        }
+                        _ => {
+                            // reconsume in the comment state, which also covers eof
+                            self.switch_state(HTMLTokenizerState::Comment);
+                            self.reconsume = true;
+                        }
+                    }
+                }
+
+                // Comment Start Dash state
+                HTMLTokenizerState::CommentStartDash => {
+                    match self.consume_next_input_character() {
+                        Some('-') => {
+                            self.switch_state(HTMLTokenizerState::CommentEnd);
+                        }
+                        Some('>') => {
+                            self.emit_parse_error(ParseError::AbruptClosingOfEmptyComment);
+                            self.switch_state(HTMLTokenizerState::Data);
+                            self.emit_current_comment_token();
+                        }
+                        Some(_) => {
+                            self.append_to_current_comment('-');
+                            self.switch_state(HTMLTokenizerState::Comment);
+                            self.reconsume = true;
+                        }
+                        None => {
+                            self.eof_in_comment();
+                            eof_reached!(self);
+                        }
                     }
                 }
 
                 // Comment state
                 HTMLTokenizerState::Comment => {
+                    if let Some(run) = self.plain_run(b'<', b'-', b'\r') {
+                        self.append_run_to_current_comment(run);
+                        continue;
+                    }
                     if let Some(current_input_character) =
-                        self.consume_next_input_character().clone()
+                        self.consume_next_input_character()
                     {
                         match current_input_character {
-                            '<' => {}
+                            '<' => {
+                                self.append_to_current_comment(current_input_character);
+                                self.switch_state(HTMLTokenizerState::CommentLessThanSign);
+                            }
                             '-' => self.switch_state(HTMLTokenizerState::CommentEndDash),
-                            '\0' => {}
+                            '\0' => {
+                                self.emit_parse_error(ParseError::UnexpectedNullCharacter);
+                                self.append_to_current_comment(char::REPLACEMENT_CHARACTER);
+                            }
                             _ => {
                                 // append the current_input_character to the existing comment
                                 // token's data
-                                if let Some(comment_token) = &mut self.current_comment_token {
-                                    comment_token.data.push(current_input_character);
-                                }
+                                self.append_to_current_comment(current_input_character);
                             }
                         }
                     } else {
-                        eof_reached!("end of file reached");
+                        self.eof_in_comment();
+                        eof_reached!(self);
+                    }
+                }
+
+                // Comment Less-Than Sign state
+                HTMLTokenizerState::CommentLessThanSign => {
+                    match self.consume_next_input_character() {
+                        Some('!') => {
+                            self.append_to_current_comment('!');
+                            self.switch_state(HTMLTokenizerState::CommentLessThanSignBang);
+                        }
+                        Some('<') => {
+                            self.append_to_current_comment('<');
+                        }
+                        _ => {
+                            self.switch_state(HTMLTokenizerState::Comment);
+                            self.reconsume = true;
+                        }
                     }
                 }
 
+                // Comment Less-Than Sign Bang state
+                HTMLTokenizerState::CommentLessThanSignBang => {
+                    match self.consume_next_input_character() {
+                        Some('-') => {
+                            self.switch_state(HTMLTokenizerState::CommentLessThanSignBangDash);
+                        }
+                        _ => {
+                            self.switch_state(HTMLTokenizerState::Comment);
+                            self.reconsume = true;
+                        }
+                    }
+                }
+
+                // Comment Less-Than Sign Bang Dash state
+                HTMLTokenizerState::CommentLessThanSignBangDash => {
+                    match self.consume_next_input_character() {
+                        Some('-') => {
+                            self.switch_state(HTMLTokenizerState::CommentLessThanSignBangDashDash);
+                        }
+                        _ => {
+                            self.switch_state(HTMLTokenizerState::CommentEndDash);
+                            self.reconsume = true;
+                        }
+                    }
+                }
+
+                // Comment Less-Than Sign Bang Dash Dash state
+                HTMLTokenizerState::CommentLessThanSignBangDashDash => {
+                    match self.consume_next_input_character() {
+                        Some('>') | None => {}
+                        Some(_) => {
+                            // a `<!--` inside of a comment
+                            self.emit_parse_error(ParseError::NestedComment);
+                        }
+                    }
+                    self.switch_state(HTMLTokenizerState::CommentEnd);
+                    self.reconsume = true;
+                }
+
                 // Comment End Dash state
                 HTMLTokenizerState::CommentEndDash => {
-                    if let Some(current_input_character) =
-                        self.consume_next_input_character().clone()
-                    {
-                        match current_input_character {
-                            '-' => self.switch_state(HTMLTokenizerState::CommentEnd),
-                            _ => {}
+                    match self.consume_next_input_character() {
+                        Some('-') => {
+                            self.switch_state(HTMLTokenizerState::CommentEnd);
+                        }
+                        Some(_) => {
+                            // the dash was part of the comment after all
+                            self.append_to_current_comment('-');
+                            self.switch_state(HTMLTokenizerState::Comment);
+                            self.reconsume = true;
+                        }
+                        None => {
+                            self.eof_in_comment();
+                            eof_reached!(self);
                         }
-                    } else {
-                        eof_reached!("end of file reached");
                     }
                 }
 
                 // Comment End state
                 HTMLTokenizerState::CommentEnd => {
                     if let Some(current_input_character) =
-                        self.consume_next_input_character().clone()
+                        self.consume_next_input_character()
                     {
                         match current_input_character {
                             '>' => {
-                                //
                                 self.switch_state(HTMLTokenizerState::Data);
                                 // emit the current comment token.
                                 self.emit_current_comment_token();
                             }
-                            '!' => {}
-                            '-' => {}
-                            _ => {}
+                            '!' => {
+                                self.switch_state(HTMLTokenizerState::CommentEndBang);
+                            }
+                            '-' => {
+                                self.append_to_current_comment('-');
+                            }
+                            _ => {
+                                self.append_to_current_comment('-');
+                                self.append_to_current_comment('-');
+                                self.switch_state(HTMLTokenizerState::Comment);
+                                self.reconsume = true;
+                            }
                         }
                     } else {
-                        eof_reached!("end of file reached");
+                        self.eof_in_comment();
+                        eof_reached!(self);
+                    }
+                }
+
+                // Comment End Bang state
+                HTMLTokenizerState::CommentEndBang => {
+                    match self.consume_next_input_character() {
+                        Some('-') => {
+                            for character in "--!".chars() {
+                                self.append_to_current_comment(character);
+                            }
+                            self.switch_state(HTMLTokenizerState::CommentEndDash);
+                        }
+                        Some('>') => {
+                            self.emit_parse_error(ParseError::IncorrectlyClosedComment);
+                            self.switch_state(HTMLTokenizerState::Data);
+                            self.emit_current_comment_token();
+                        }
+                        Some(_) => {
+                            for character in "--!".chars() {
+                                self.append_to_current_comment(character);
+                            }
+                            self.switch_state(HTMLTokenizerState::Comment);
+                            self.reconsume = true;
+                        }
+                        None => {
+                            self.eof_in_comment();
+                            eof_reached!(self);
+                        }
+                    }
+                }
+
+                // CDATA Section state
+                HTMLTokenizerState::CDATASection => {
+                    match self.consume_next_input_character() {
+                        Some(']') => {
+                            self.switch_state(HTMLTokenizerState::CDATASectionBracket);
+                        }
+                        Some(current_input_character) => {
+                            // NULL characters are emitted as they are, the tree construction
+                            // stage deals with them
+                            self.emit_character_token(current_input_character);
+                        }
+                        None => {
+                            self.emit_parse_error(ParseError::EofInCdata);
+                            eof_reached!(self);
+                        }
+                    }
+                }
+
+                // CDATA Section Bracket state
+                HTMLTokenizerState::CDATASectionBracket => {
+                    match self.consume_next_input_character() {
+                        Some(']') => {
+                            self.switch_state(HTMLTokenizerState::CDATASectionEnd);
+                        }
+                        _ => {
+                            self.emit_character_token(']');
+                            self.switch_state(HTMLTokenizerState::CDATASection);
+                            self.reconsume = true;
+                        }
+                    }
+                }
+
+                // CDATA Section End state
+                HTMLTokenizerState::CDATASectionEnd => {
+                    match self.consume_next_input_character() {
+                        Some(']') => {
+                            self.emit_character_token(']');
+                        }
+                        Some('>') => {
+                            self.switch_state(HTMLTokenizerState::Data);
+                        }
+                        _ => {
+                            self.emit_character_token(']');
+                            self.emit_character_token(']');
+                            self.switch_state(HTMLTokenizerState::CDATASection);
+                            self.reconsume = true;
+                        }
                     }
                 }
 
@@ -569,13 +1648,21 @@ impl HTMLTokenizer {
                             ' ' | '\t' | '\n' | '\x0C' => {
                                 self.switch_state(HTMLTokenizerState::BeforeDOCTYPEName)
                             }
-                            '>' => {}
+                            '>' => {
+                                self.switch_state(HTMLTokenizerState::BeforeDOCTYPEName);
+                                self.reconsume = true;
+                            }
                             _ => {
-                                // just ignore it
+                                self.emit_parse_error(ParseError::MissingWhitespaceBeforeDoctypeName);
+                                self.switch_state(HTMLTokenizerState::BeforeDOCTYPEName);
+                                self.reconsume = true;
                             }
                         }
                     } else {
-                        eof_reached!("end of file reached");
+                        // a DOCTYPE token is created just to be emitted in quirks mode
+                        self.current_doctype_token = Some(DOCTYPE::default());
+                        self.eof_in_doctype();
+                        eof_reached!(self);
                     }
                 }
 
@@ -587,27 +1674,37 @@ impl HTMLTokenizer {
                                 // ignore the character which probably means don't do anything?
                                 continue;
                             }
-                            'A'..='Z' => {}
                             '\0' => {
-                                panic!("encountered a null character");
+                                self.emit_parse_error(ParseError::UnexpectedNullCharacter);
+                                self.create_doctype_token_with_name(char::REPLACEMENT_CHARACTER);
+                                self.switch_state(HTMLTokenizerState::DOCTYPEName);
                             }
-                            '>' => {}
-                            _ => {
-                                // create a new DOCTYPE token
+                            '>' => {
+                                self.emit_parse_error(ParseError::MissingDoctypeName);
                                 self.current_doctype_token = Some(DOCTYPE {
-                                    name: Some(
-                                        self.current_input_character.unwrap().to_string().clone(),
-                                    ),
-                                    force_quirks: false,
+                                    name: None,
+                                    force_quirks: true,
                                     public_identifier: None,
                                     system_identifier: None,
+                                    original_name: None,
+                                    span: SourceSpan::default(),
                                 });
+                                self.switch_state(HTMLTokenizerState::Data);
+                                self.emit_current_doctype_token();
+                            }
+                            _ => {
+                                // create a new DOCTYPE token, whose name starts with the current
+                                // input character (in lowercase if it's an uppercase ASCII letter)
+                                self.create_doctype_token_with_name(current_input_character);
                                 // switch to DOCTYPE name state
                                 self.switch_state(HTMLTokenizerState::DOCTYPEName);
                             }
                         }
                     } else {
-                        eof_reached!("end of file reached");
+                        // a DOCTYPE token is created just to be emitted in quirks mode
+                        self.current_doctype_token = Some(DOCTYPE::default());
+                        self.eof_in_doctype();
+                        eof_reached!(self);
                     }
                 }
 
@@ -623,56 +1720,453 @@ impl HTMLTokenizer {
                                 // emit current doctype token
                                 self.emit_current_doctype_token();
                             }
-                            'A'..='Z' => {
-                                // ignore for now
+                            '\0' => {
+                                self.emit_parse_error(ParseError::UnexpectedNullCharacter);
+                                self.append_to_current_doctype_name(char::REPLACEMENT_CHARACTER);
+                                self.report_doctype_name();
                             }
+                            _ => {
+                                // uppercase ASCII letters are appended in lowercase
+                                self.append_to_current_doctype_name(current_input_character);
+                                self.report_doctype_name();
+                            }
+                        }
+                    } else {
+                        self.eof_in_doctype();
+                        eof_reached!(self);
+                    }
+                }
+
+                // After DOCTYPE Name state
+                HTMLTokenizerState::AfterDOCTYPEName => {
+                    if let Some(current_input_character) = self.consume_next_input_character() {
+                        match current_input_character {
+                            '\t' | '\n' | '\x0C' | ' ' => {
+                                // ignore the character;
+                                continue;
+                            }
+                            '>' => {
+                                self.switch_state(HTMLTokenizerState::Data);
+                                self.emit_current_doctype_token();
+                            }
+                            'P' | 'p' if self.next_few_characters_are("UBLIC".to_string()) => {
+                                self.consume_substring("UBLIC".to_string());
+                                self.switch_state(HTMLTokenizerState::AfterDOCTYPEPublicKeyword);
+                            }
+                            'S' | 's' if self.next_few_characters_are("YSTEM".to_string()) => {
+                                self.consume_substring("YSTEM".to_string());
+                                self.switch_state(HTMLTokenizerState::AfterDOCTYPESystemKeyword);
+                            }
+                            _ => {
+                                self.emit_parse_error(ParseError::InvalidCharacterSequenceAfterDoctypeName);
+                                self.set_current_doctype_force_quirks();
+                                self.switch_state(HTMLTokenizerState::BogusDOCTYPE);
+                                self.reconsume = true;
+                            }
+                        }
+                    } else {
+                        self.eof_in_doctype();
+                        eof_reached!(self);
+                    }
+                }
+
+                // After DOCTYPE public keyword state
+                HTMLTokenizerState::AfterDOCTYPEPublicKeyword => {
+                    if self.consume_next_input_character().is_some() {
+                        self.after_doctype_keyword_state(DoctypeIdentifier::Public);
+                    } else {
+                        self.eof_in_doctype();
+                        eof_reached!(self);
+                    }
+                }
+
+                // Before DOCTYPE public identifier state
+                HTMLTokenizerState::BeforeDOCTYPEPublicIdentifier => {
+                    if self.consume_next_input_character().is_some() {
+                        self.before_doctype_identifier_state(DoctypeIdentifier::Public);
+                    } else {
+                        self.eof_in_doctype();
+                        eof_reached!(self);
+                    }
+                }
+
+                // DOCTYPE public identifier (double-quoted) state
+                HTMLTokenizerState::DOCTYPEPublicIdentifierDoubleQuoted => {
+                    if self.consume_next_input_character().is_some() {
+                        self.doctype_identifier_quoted_state(DoctypeIdentifier::Public, '"');
+                    } else {
+                        self.eof_in_doctype();
+                        eof_reached!(self);
+                    }
+                }
+
+                // DOCTYPE public identifier (single-quoted) state
+                HTMLTokenizerState::DOCTYPEPublicIdentifierSingleQuoted => {
+                    if self.consume_next_input_character().is_some() {
+                        self.doctype_identifier_quoted_state(DoctypeIdentifier::Public, '\'');
+                    } else {
+                        self.eof_in_doctype();
+                        eof_reached!(self);
+                    }
+                }
+
+                // After DOCTYPE public identifier state
+                HTMLTokenizerState::AfterDOCTYPEPublicIdentifier => {
+                    if let Some(current_input_character) = self.consume_next_input_character() {
+                        match current_input_character {
+                            '\t' | '\n' | '\x0C' | ' ' => {
+                                self.switch_state(
+                                    HTMLTokenizerState::BetweenDOCTYPEPublicAndSystemIdentifiers,
+                                );
+                            }
+                            '>' => {
+                                self.switch_state(HTMLTokenizerState::Data);
+                                self.emit_current_doctype_token();
+                            }
+                            '"' | '\'' => {
+                                self.emit_parse_error(
+                                    ParseError::MissingWhitespaceBetweenDoctypePublicAndSystemIdentifiers,
+                                );
+                                self.start_doctype_identifier(DoctypeIdentifier::System, current_input_character);
+                            }
+                            _ => {
+                                self.emit_parse_error(ParseError::MissingQuoteBeforeDoctypeSystemIdentifier);
+                                self.set_current_doctype_force_quirks();
+                                self.switch_state(HTMLTokenizerState::BogusDOCTYPE);
+                                self.reconsume = true;
+                            }
+                        }
+                    } else {
+                        self.eof_in_doctype();
+                        eof_reached!(self);
+                    }
+                }
+
+                // Between DOCTYPE public and system identifiers state
+                HTMLTokenizerState::BetweenDOCTYPEPublicAndSystemIdentifiers => {
+                    if let Some(current_input_character) = self.consume_next_input_character() {
+                        match current_input_character {
+                            '\t' | '\n' | '\x0C' | ' ' => {
+                                // ignore the character
+                                continue;
+                            }
+                            '>' => {
+                                self.switch_state(HTMLTokenizerState::Data);
+                                self.emit_current_doctype_token();
+                            }
+                            '"' | '\'' => {
+                                self.start_doctype_identifier(DoctypeIdentifier::System, current_input_character);
+                            }
+                            _ => {
+                                self.emit_parse_error(ParseError::MissingQuoteBeforeDoctypeSystemIdentifier);
+                                self.set_current_doctype_force_quirks();
+                                self.switch_state(HTMLTokenizerState::BogusDOCTYPE);
+                                self.reconsume = true;
+                            }
+                        }
+                    } else {
+                        self.eof_in_doctype();
+                        eof_reached!(self);
+                    }
+                }
+
+                // After DOCTYPE system keyword state
+                HTMLTokenizerState::AfterDOCTYPESystemKeyword => {
+                    if self.consume_next_input_character().is_some() {
+                        self.after_doctype_keyword_state(DoctypeIdentifier::System);
+                    } else {
+                        self.eof_in_doctype();
+                        eof_reached!(self);
+                    }
+                }
+
+                // Before DOCTYPE system identifier state
+                HTMLTokenizerState::BeforeDOCTYPESystemIdentifier => {
+                    if self.consume_next_input_character().is_some() {
+                        self.before_doctype_identifier_state(DoctypeIdentifier::System);
+                    } else {
+                        self.eof_in_doctype();
+                        eof_reached!(self);
+                    }
+                }
+
+                // DOCTYPE system identifier (double-quoted) state
+                HTMLTokenizerState::DOCTYPESystemIdentifierDoubleQuoted => {
+                    if self.consume_next_input_character().is_some() {
+                        self.doctype_identifier_quoted_state(DoctypeIdentifier::System, '"');
+                    } else {
+                        self.eof_in_doctype();
+                        eof_reached!(self);
+                    }
+                }
+
+                // DOCTYPE system identifier (single-quoted) state
+                HTMLTokenizerState::DOCTYPESystemIdentifierSingleQuoted => {
+                    if self.consume_next_input_character().is_some() {
+                        self.doctype_identifier_quoted_state(DoctypeIdentifier::System, '\'');
+                    } else {
+                        self.eof_in_doctype();
+                        eof_reached!(self);
+                    }
+                }
+
+                // After DOCTYPE system identifier state
+                HTMLTokenizerState::AfterDOCTYPESystemIdentifier => {
+                    if let Some(current_input_character) = self.consume_next_input_character() {
+                        match current_input_character {
+                            '\t' | '\n' | '\x0C' | ' ' => {
+                                // ignore the character
+                                continue;
+                            }
+                            '>' => {
+                                self.switch_state(HTMLTokenizerState::Data);
+                                self.emit_current_doctype_token();
+                            }
+                            _ => {
+                                // this does not set the force-quirks flag
+                                self.emit_parse_error(
+                                    ParseError::UnexpectedCharacterAfterDoctypeSystemIdentifier,
+                                );
+                                self.switch_state(HTMLTokenizerState::BogusDOCTYPE);
+                                self.reconsume = true;
+                            }
+                        }
+                    } else {
+                        self.eof_in_doctype();
+                        eof_reached!(self);
+                    }
+                }
+
+                // Bogus DOCTYPE state
+                HTMLTokenizerState::BogusDOCTYPE => {
+                    if let Some(current_input_character) = self.consume_next_input_character() {
+                        match current_input_character {
+                            '>' => {
+                                self.switch_state(HTMLTokenizerState::Data);
+                                self.emit_current_doctype_token();
+                            }
+                            '\0' => {
+                                self.emit_parse_error(ParseError::UnexpectedNullCharacter);
+                            }
+                            _ => {
+                                // ignore the character
+                            }
+                        }
+                    } else {
+                        // the doctype is emitted as it is, without setting the force-quirks flag
+                        self.emit_current_doctype_token();
+                        eof_reached!(self);
+                    }
+                }
+
+                // Character Reference state
+                HTMLTokenizerState::CharacterReference => {
+                    self.temporary_buffer = "&".to_string();
+                    match self.consume_next_input_character() {
+                        Some(current_input_character) if current_input_character.is_ascii_alphanumeric() => {
+                            // reconsume in the named character reference state
+                            self.switch_state(HTMLTokenizerState::NamedCharacterReference);
+                            self.reconsume = true;
+                        }
+                        Some('#') => {
+                            self.temporary_buffer.push('#');
+                            self.switch_state(HTMLTokenizerState::NumericCharacterReference);
+                        }
+                        _ => {
+                            // this also covers eof
+                            self.flush_code_points_consumed_as_a_character_reference();
+                            self.switch_to_return_state();
+                            self.reconsume = true;
+                        }
+                    }
+                }
+
+                // Named Character Reference state
+                HTMLTokenizerState::NamedCharacterReference => {
+                    if let Some((name, value)) = self.longest_matching_named_character_reference() {
+                        // consume the characters that make up the name, they go into the temporary
+                        // buffer in case they have to be flushed as they are
+                        for _ in 0..name.len() {
+                            if let Some(current_input_character) = self.consume_next_input_character() {
+                                self.temporary_buffer.push(current_input_character);
+                            }
+                        }
+
+                        let is_followed_by_equals_sign_or_alphanumeric = matches!(
+                            self.next_input_character,
+                            Some(next_input_character) if next_input_character == '=' || next_input_character.is_ascii_alphanumeric()
+                        );
+                        if self.is_consumed_as_part_of_an_attribute()
+                            && !name.ends_with(';')
+                            && is_followed_by_equals_sign_or_alphanumeric
+                        {
+                            // for historical reasons, something like `&copy=` in an attribute value
+                            // is left as it is
+                            self.flush_code_points_consumed_as_a_character_reference();
+                        } else {
+                            if !name.ends_with(';') {
+                                self.emit_parse_error(ParseError::MissingSemicolonAfterCharacterReference);
+                            }
+                            self.temporary_buffer = value.to_string();
+                            self.flush_code_points_consumed_as_a_character_reference();
+                        }
+                        self.switch_to_return_state();
+                    } else {
+                        // only the `&` has been consumed, the ambiguous ampersand state takes care of
+                        // the alphanumeric characters that follow it
+                        self.flush_code_points_consumed_as_a_character_reference();
+                        self.switch_state(HTMLTokenizerState::AmbiguousAmpersand);
+                    }
+                }
+
+                // Ambiguous Ampersand state
+                HTMLTokenizerState::AmbiguousAmpersand => {
+                    match self.consume_next_input_character() {
+                        Some(current_input_character) if current_input_character.is_ascii_alphanumeric() => {
+                            if self.is_consumed_as_part_of_an_attribute() {
+                                self.append_current_input_character_to_current_attribute_value();
+                            } else {
+                                self.emit_character_token(current_input_character);
+                            }
+                        }
+                        Some(';') => {
+                            self.emit_parse_error(ParseError::UnknownNamedCharacterReference);
+                            self.switch_to_return_state();
+                            self.reconsume = true;
+                        }
+                        _ => {
+                            self.switch_to_return_state();
+                            self.reconsume = true;
+                        }
+                    }
+                }
+
+                // Numeric Character Reference state
+                HTMLTokenizerState::NumericCharacterReference => {
+                    self.character_reference_code = 0;
+                    match self.consume_next_input_character() {
+                        Some(current_input_character @ ('x' | 'X')) => {
+                            self.temporary_buffer.push(current_input_character);
+                            self.switch_state(HTMLTokenizerState::HexadecimalCharacterReferenceStart);
+                        }
+                        _ => {
+                            self.switch_state(HTMLTokenizerState::DecimalCharacterReferenceStart);
+                            self.reconsume = true;
+                        }
+                    }
+                }
 
-                            '\0' => {
-                                // ignore for now
-                            }
+                // Hexadecimal Character Reference Start state
+                HTMLTokenizerState::HexadecimalCharacterReferenceStart => {
+                    match self.consume_next_input_character() {
+                        Some(current_input_character) if current_input_character.is_ascii_hexdigit() => {
+                            self.switch_state(HTMLTokenizerState::HexadecimalCharacterReference);
+                            self.reconsume = true;
+                        }
+                        _ => {
+                            self.emit_parse_error(ParseError::AbsenceOfDigitsInNumericCharacterReference);
+                            self.flush_code_points_consumed_as_a_character_reference();
+                            self.switch_to_return_state();
+                            self.reconsume = true;
+                        }
+                    }
+                }
 
-                            _ => {
-                                // append current_input_character to current_doctype_token's name
-                                if let Some(doctype_token) = &mut self.current_doctype_token {
-                                    if let Some(name) = &mut doctype_token.name {
-                                        name.push(self.current_input_character.unwrap().clone());
+                // Decimal Character Reference Start state
+                HTMLTokenizerState::DecimalCharacterReferenceStart => {
+                    match self.consume_next_input_character() {
+                        Some(current_input_character) if current_input_character.is_ascii_digit() => {
+                            self.switch_state(HTMLTokenizerState::DecimalCharacterReference);
+                            self.reconsume = true;
+                        }
+                        _ => {
+                            self.emit_parse_error(ParseError::AbsenceOfDigitsInNumericCharacterReference);
+                            self.flush_code_points_consumed_as_a_character_reference();
+                            self.switch_to_return_state();
+                            self.reconsume = true;
+                        }
+                    }
+                }
 
-                                        println!("name: {}", name);
-                                    }
-                                }
-                            }
+                // Hexadecimal Character Reference state
+                HTMLTokenizerState::HexadecimalCharacterReference => {
+                    match self.consume_next_input_character() {
+                        Some(current_input_character) if current_input_character.is_ascii_hexdigit() => {
+                            // saturate instead of overflowing, anything this big is out of range anyway
+                            let digit = current_input_character.to_digit(16).unwrap();
+                            self.character_reference_code = self
+                                .character_reference_code
+                                .saturating_mul(16)
+                                .saturating_add(digit);
+                        }
+                        Some(';') => {
+                            self.switch_state(HTMLTokenizerState::NumericCharacterReferenceEnd);
+                        }
+                        _ => {
+                            self.emit_parse_error(ParseError::MissingSemicolonAfterCharacterReference);
+                            self.switch_state(HTMLTokenizerState::NumericCharacterReferenceEnd);
+                            self.reconsume = true;
                         }
-                    } else {
-                        eof_reached!("end of file reached");
                     }
                 }
 
-                // After DOCTYPE Name state
-                HTMLTokenizerState::AfterDOCTYPEName => {
-                    if let Some(current_input_character) = self.consume_next_input_character() {
-                        match current_input_character {
-                            '\t' | '\n' | '\x0C' | ' ' => {
-                                // ignore the character;
-                                continue;
-                            }
-                            '>' => {
-                                self.switch_state(HTMLTokenizerState::Data);
-                                self.emit_current_doctype_token();
-                            }
-                            _ => {}
+                // Decimal Character Reference state
+                HTMLTokenizerState::DecimalCharacterReference => {
+                    match self.consume_next_input_character() {
+                        Some(current_input_character) if current_input_character.is_ascii_digit() => {
+                            let digit = current_input_character.to_digit(10).unwrap();
+                            self.character_reference_code = self
+                                .character_reference_code
+                                .saturating_mul(10)
+                                .saturating_add(digit);
+                        }
+                        Some(';') => {
+                            self.switch_state(HTMLTokenizerState::NumericCharacterReferenceEnd);
+                        }
+                        _ => {
+                            self.emit_parse_error(ParseError::MissingSemicolonAfterCharacterReference);
+                            self.switch_state(HTMLTokenizerState::NumericCharacterReferenceEnd);
+                            self.reconsume = true;
                         }
                     }
                 }
 
-                // catch any unimplemented state and stop the tokenization process
-                _ => {
-                    unknown_state_reached!(self.state);
+                // Numeric Character Reference End state
+                HTMLTokenizerState::NumericCharacterReferenceEnd => {
+                    // this state doesn't consume anything. If we got here by reconsuming, the
+                    // reconsume flag is left alone so that the return state picks the character up
+                    let code = self.character_reference_code;
+                    let character = match code {
+                        0x00 => {
+                            self.emit_parse_error(ParseError::NullCharacterReference);
+                            char::REPLACEMENT_CHARACTER
+                        }
+                        0x110000.. => {
+                            self.emit_parse_error(ParseError::CharacterReferenceOutsideUnicodeRange);
+                            char::REPLACEMENT_CHARACTER
+                        }
+                        0xD800..=0xDFFF => {
+                            self.emit_parse_error(ParseError::SurrogateCharacterReference);
+                            char::REPLACEMENT_CHARACTER
+                        }
+                        _ => {
+                            if is_noncharacter(code) {
+                                self.emit_parse_error(ParseError::NoncharacterCharacterReference);
+                            }
+                            if code == 0x0D || (is_control(code) && !is_ascii_whitespace(code)) {
+                                self.emit_parse_error(ParseError::ControlCharacterReference);
+                            }
+                            windows_1252_replacement(code)
+                                .or_else(|| char::from_u32(code))
+                                .unwrap_or(char::REPLACEMENT_CHARACTER)
+                        }
+                    };
+                    self.temporary_buffer = character.to_string();
+                    self.flush_code_points_consumed_as_a_character_reference();
+                    self.switch_to_return_state();
                 }
             }
         }
-
-        // print the tokens
-        println!("tokens: {:#?}", self.tokens);
     }
 
 
@@ -680,39 +2174,608 @@ impl HTMLTokenizer {
     fn append_current_input_character_to_current_attribute_value(&mut self){
         if let Some(tag_token) = &mut self.current_tag_token{
             match tag_token{
-                Tag::StartTag { tag_name: _, self_closing: _, attributes }
+                Tag::StartTag { tag_name: _, self_closing: _, attributes, original_tag_name: _, span: _ }
                 |
-                Tag::EndTag { tag_name: _, self_closing: _, attributes } =>  {
+                Tag::EndTag { tag_name: _, self_closing: _, attributes, original_tag_name: _, span: _ } =>  {
                     if let Some(current_attribute) = attributes.last_mut(){
-                        current_attribute.value.push(self.current_input_character.clone().unwrap());
+                        push_to_text(
+                            &self.input,
+                            &mut current_attribute.value,
+                            self.current_input_character.unwrap(),
+                            self.current_position.offset,
+                        );
                     }
                 }
             }
         }
     }
 
-    fn emit_token(&mut self, token: HTMLToken) {
-        self.tokens.push(token);
+    /// Adds an attribute with an empty name and value to the current tag token, its name starting
+    /// at the current input character. If the attribute before it turned out to be a duplicate,
+    /// that one is dropped first
+    fn start_new_attribute(&mut self) {
+        self.drop_current_attribute_if_duplicate();
+        if let Some(Tag::StartTag { attributes, .. } | Tag::EndTag { attributes, .. }) =
+            &mut self.current_tag_token
+        {
+            attributes.push(Attribute {
+                key: Cow::Borrowed(""),
+                value: Cow::Borrowed(""),
+                original_key: None,
+                key_span: SourceSpan {
+                    start: self.current_position,
+                    end: self.current_position,
+                },
+                value_span: None,
+                namespace: None,
+            });
+        }
+    }
+
+    /// Appends to the attribute name in lowercase, and as it is to the original name if that's
+    /// being kept
+    fn append_to_current_attribute_name(&mut self, character: char) {
+        let preserve_original_case = self.preserve_original_case;
+        let offset = self.current_position.offset;
+        if let Some(Tag::StartTag { attributes, .. } | Tag::EndTag { attributes, .. }) =
+            &mut self.current_tag_token
+        {
+            if let Some(current_attribute) = attributes.last_mut() {
+                push_to_text(
+                    &self.input,
+                    &mut current_attribute.key,
+                    character.to_ascii_lowercase(),
+                    offset,
+                );
+                if preserve_original_case {
+                    let original_key = current_attribute.original_key.get_or_insert(Cow::Borrowed(""));
+                    push_to_text(&self.input, original_key, character, offset);
+                }
+            }
+        }
+    }
+
+    fn current_attribute_mut(&mut self) -> Option<&mut Attribute<'a>> {
+        match &mut self.current_tag_token {
+            Some(Tag::StartTag { attributes, .. } | Tag::EndTag { attributes, .. }) => {
+                attributes.last_mut()
+            }
+            None => None,
+        }
+    }
+
+    /// The attribute name ends right before the current input character, which is the first one
+    /// that isn't part of it
+    fn end_current_attribute_name(&mut self) {
+        let end = self.current_position;
+        if let Some(current_attribute) = self.current_attribute_mut() {
+            current_attribute.key_span.end = end;
+        }
+    }
+
+    fn start_current_attribute_value(&mut self, start: SourcePosition) {
+        if let Some(current_attribute) = self.current_attribute_mut() {
+            current_attribute.value_span = Some(SourceSpan { start, end: start });
+        }
+    }
+
+    /// The attribute value ends right before the current input character, which is either the
+    /// closing quote or what ended an unquoted value
+    fn end_current_attribute_value(&mut self) {
+        let end = self.current_position;
+        if let Some(SourceSpan { end: value_end, .. }) =
+            self.current_attribute_mut().and_then(|attribute| attribute.value_span.as_mut())
+        {
+            *value_end = end;
+        }
+    }
+
+    /// Called when leaving the attribute name state, once the name is complete. An attribute with
+    /// the same name as one before it is a parse error and gets dropped from the tag (after its
+    /// value has been read, so that the value doesn't end up anywhere else)
+    fn check_for_duplicate_attribute(&mut self) {
+        if let Some(Tag::StartTag { attributes, .. } | Tag::EndTag { attributes, .. }) =
+            &self.current_tag_token
+        {
+            if let Some((current_attribute, earlier_attributes)) = attributes.split_last() {
+                if earlier_attributes
+                    .iter()
+                    .any(|attribute| attribute.key == current_attribute.key)
+                {
+                    self.current_attribute_is_duplicate = true;
+                    self.emit_parse_error(ParseError::DuplicateAttribute);
+                }
+            }
+        }
+    }
+
+    fn drop_current_attribute_if_duplicate(&mut self) {
+        if !self.current_attribute_is_duplicate {
+            return;
+        }
+        self.current_attribute_is_duplicate = false;
+        if let Some(Tag::StartTag { attributes, .. } | Tag::EndTag { attributes, .. }) =
+            &mut self.current_tag_token
+        {
+            attributes.pop();
+        }
+    }
+
+    fn append_to_current_attribute_value(&mut self, string: &str) {
+        if let Some(Tag::StartTag { attributes, .. } | Tag::EndTag { attributes, .. }) =
+            &mut self.current_tag_token
+        {
+            if let Some(current_attribute) = attributes.last_mut() {
+                for character in string.chars() {
+                    push_to_text(
+                        &self.input,
+                        &mut current_attribute.value,
+                        character,
+                        self.current_position.offset,
+                    );
+                }
+            }
+        }
+    }
+
+    /// An appropriate end tag token is an end tag token whose tag name matches the tag name of
+    /// the last start tag to have been emitted from this tokenizer
+    fn is_appropriate_end_tag_token(&self) -> bool {
+        match (&self.current_tag_token, &self.last_start_tag_name) {
+            (Some(Tag::EndTag { tag_name, .. }), Some(last_start_tag_name)) => {
+                tag_name == last_start_tag_name
+            }
+            _ => false,
+        }
+    }
+
+    /// Like the end tag name states, the RCDATA, RAWTEXT and script data end tag open states only
+    /// differ in where they go next. This is called after the current input character has been
+    /// consumed
+    fn end_tag_open_state(
+        &mut self,
+        end_tag_name_state: HTMLTokenizerState,
+        text_state: HTMLTokenizerState,
+    ) {
+        match self.current_input_character {
+            Some('a'..='z' | 'A'..='Z') => {
+                // create an end tag token and reconsume in the end tag name state
+                self.current_tag_token = Some(Tag::EndTag {
+                    tag_name: Cow::Borrowed(""),
+                    self_closing: false,
+                    attributes: Vec::new(),
+                    original_tag_name: None,
+                    span: SourceSpan::default(),
+                });
+                self.switch_state(end_tag_name_state);
+                self.reconsume = true;
+            }
+            _ => {
+                self.emit_character_token('<');
+                self.emit_character_token('/');
+                self.switch_state(text_state);
+                self.reconsume = true;
+            }
+        }
+    }
+
+    /// The script data double escape start and double escape end states look the same: they
+    /// collect a tag name into the temporary buffer and, once the name ends, go to
+    /// `script_state` if that name was "script" or to `other_state` if it wasn't (which is also
+    /// where anything that isn't part of a tag name is reconsumed). This is called after the
+    /// current input character has been consumed
+    fn double_escape_boundary_state(
+        &mut self,
+        script_state: HTMLTokenizerState,
+        other_state: HTMLTokenizerState,
+    ) {
+        match self.current_input_character {
+            Some(current_input_character @ ('\t' | '\n' | '\x0C' | ' ' | '/' | '>')) => {
+                if self.temporary_buffer == "script" {
+                    self.switch_state(script_state);
+                } else {
+                    self.switch_state(other_state);
+                }
+                self.emit_character_token(current_input_character);
+            }
+            Some(current_input_character @ ('a'..='z' | 'A'..='Z')) => {
+                self.temporary_buffer.push(current_input_character.to_ascii_lowercase());
+                self.emit_character_token(current_input_character);
+            }
+            _ => {
+                self.switch_state(other_state);
+                self.reconsume = true;
+            }
+        }
+    }
+
+    /// The RCDATA, RAWTEXT and script data end tag name states only differ in the state they go
+    /// back to when what looked like an end tag turns out to be text. This is called after the
+    /// current input character has been consumed
+    fn end_tag_name_state(&mut self, text_state: HTMLTokenizerState) {
+        match self.current_input_character {
+            Some('\t' | '\n' | '\x0C' | ' ') if self.is_appropriate_end_tag_token() => {
+                self.switch_state(HTMLTokenizerState::BeforeAttributeName);
+            }
+            Some('/') if self.is_appropriate_end_tag_token() => {
+                self.switch_state(HTMLTokenizerState::SelfClosingStartTag);
+            }
+            Some('>') if self.is_appropriate_end_tag_token() => {
+                self.switch_state(HTMLTokenizerState::Data);
+                self.emit_current_tag_token();
+            }
+            Some(current_input_character @ ('a'..='z' | 'A'..='Z')) => {
+                self.append_to_current_tag_name(current_input_character);
+                self.temporary_buffer.push(current_input_character);
+            }
+            _ => {
+                // it wasn't an end tag after all, so everything consumed so far is just text
+                self.current_tag_token = None;
+                self.emit_character_token('<');
+                self.emit_character_token('/');
+                let temporary_buffer = std::mem::take(&mut self.temporary_buffer);
+                for character in temporary_buffer.chars() {
+                    self.emit_character_token(character);
+                }
+                self.switch_state(text_state);
+                self.reconsume = true;
+            }
+        }
+    }
+
+    /// Appends to the tag name in lowercase, and as it is to the original tag name if that's being
+    /// kept
+    fn append_to_current_tag_name(&mut self, character: char) {
+        let preserve_original_case = self.preserve_original_case;
+        let offset = self.current_position.offset;
+        if let Some(
+            Tag::StartTag {
+                tag_name,
+                original_tag_name,
+                ..
+            }
+            | Tag::EndTag {
+                tag_name,
+                original_tag_name,
+                ..
+            },
+        ) = &mut self.current_tag_token
+        {
+            push_to_text(&self.input, tag_name, character.to_ascii_lowercase(), offset);
+            if preserve_original_case {
+                let original_tag_name = original_tag_name.get_or_insert(Cow::Borrowed(""));
+                push_to_text(&self.input, original_tag_name, character, offset);
+            }
+        }
+    }
+
+    fn create_doctype_token_with_name(&mut self, character: char) {
+        self.current_doctype_token = Some(DOCTYPE {
+            name: Some(Cow::Borrowed("")),
+            force_quirks: false,
+            public_identifier: None,
+            system_identifier: None,
+            original_name: None,
+            span: SourceSpan::default(),
+        });
+        self.append_to_current_doctype_name(character);
+    }
+
+    /// Appends to the DOCTYPE name in lowercase, and as it is to the original name if that's being
+    /// kept
+    fn append_to_current_doctype_name(&mut self, character: char) {
+        let preserve_original_case = self.preserve_original_case;
+        let offset = self.current_position.offset;
+        if let Some(DOCTYPE {
+            name: Some(name),
+            original_name,
+            ..
+        }) = &mut self.current_doctype_token
+        {
+            push_to_text(&self.input, name, character.to_ascii_lowercase(), offset);
+            if preserve_original_case {
+                let original_name = original_name.get_or_insert(Cow::Borrowed(""));
+                push_to_text(&self.input, original_name, character, offset);
+            }
+        }
+    }
+
+    /// Tells the observer about the DOCTYPE name so far, see
+    /// `TokenizerObserver::doctype_name_updated`
+    fn report_doctype_name(&mut self) {
+        if let (Some(observer), Some(DOCTYPE { name: Some(name), .. })) =
+            (&mut self.observer, &self.current_doctype_token)
+        {
+            observer.doctype_name_updated(name);
+        }
+    }
+
+    fn switch_to_return_state(&mut self) {
+        let return_state = self
+            .return_state
+            .take()
+            .expect("the return state should be set before switching to it");
+        self.switch_state(return_state);
+    }
+
+    /// A character reference is consumed as part of an attribute when the return state is one of
+    /// the attribute value states
+    fn is_consumed_as_part_of_an_attribute(&self) -> bool {
+        matches!(
+            self.return_state,
+            Some(
+                HTMLTokenizerState::AttributeValueDoubleQuoted
+                    | HTMLTokenizerState::AttributeValueSingleQuoted
+                    | HTMLTokenizerState::AttributeValueUnquoted
+            )
+        )
+    }
+
+    fn flush_code_points_consumed_as_a_character_reference(&mut self) {
+        let temporary_buffer = std::mem::take(&mut self.temporary_buffer);
+        if self.is_consumed_as_part_of_an_attribute() {
+            self.append_to_current_attribute_value(&temporary_buffer);
+        } else {
+            for character in temporary_buffer.chars() {
+                self.emit_character_token(character);
+            }
+        }
+    }
+
+    /// Looks ahead (without consuming anything) for the longest name in the named character
+    /// reference table that the upcoming input characters start with
+    fn longest_matching_named_character_reference(&self) -> Option<(&'static str, &'static str)> {
+        // if we're about to reconsume, the current input character is the first one to look at
+        let start_offset = if self.reconsume {
+            self.current_position.offset
+        } else {
+            self.next_position.offset
+        };
+
+        // names are made of alphanumerics and end with an optional `;`, so there's no point looking
+        // any further than that
+        let mut candidate = String::new();
+        for character in self.input[start_offset..].chars().take(LONGEST_NAME_LENGTH) {
+            if !character.is_ascii_alphanumeric() && character != ';' {
+                break;
+            }
+            candidate.push(character);
+            if character == ';' {
+                break;
+            }
+        }
+
+        while !candidate.is_empty() {
+            if let Ok(index) = NAMED_CHARACTER_REFERENCES
+                .binary_search_by(|(name, _)| name.cmp(&candidate.as_str()))
+            {
+                return Some(NAMED_CHARACTER_REFERENCES[index]);
+            }
+            candidate.pop();
+        }
+        None
+    }
+
+    /// Characters aren't emitted one at a time. They're held back until some other token is
+    /// emitted, so that the whole run goes out as a single `Character` token
+    fn emit_character_token(&mut self, character: char) {
+        if self.split_whitespace_runs {
+            if let Some(last_character) = self.pending_characters.chars().next_back() {
+                if is_ascii_whitespace(last_character as u32) != is_ascii_whitespace(character as u32)
+                {
+                    self.pending_characters_span.end = self.token_start;
+                    self.flush_pending_characters();
+                }
+            }
+        }
+        if self.pending_characters.is_empty() {
+            self.pending_characters_span.start = self.token_start;
+        }
+        push_to_text(
+            &self.input,
+            &mut self.pending_characters,
+            character,
+            self.token_start.offset,
+        );
+        // this can be a character too far when the current input character is about to be
+        // reconsumed, emit_token cuts the run off where the next token starts
+        self.pending_characters_span.end = self.next_position;
+    }
+
+    fn flush_pending_characters(&mut self) {
+        if self.pending_characters.is_empty() {
+            return;
+        }
+        let token = HTMLToken::Character(Character {
+            data: std::mem::take(&mut self.pending_characters),
+            span: self.pending_characters_span,
+        });
+        if let Some(observer) = &mut self.observer {
+            observer.token_emitted(&token);
+        }
+        self.tokens.push_back(token);
+    }
+
+    fn emit_token(&mut self, token: HTMLToken<'a>) {
+        // any character data before this token has to come out first, and ends where it starts
+        if let Some(span) = token.span() {
+            self.pending_characters_span.end = span.start;
+        }
+        self.flush_pending_characters();
+        if let Some(observer) = &mut self.observer {
+            observer.token_emitted(&token);
+        }
+        self.tokens.push_back(token);
     }
 
     fn emit_current_tag_token(&mut self) {
-        if let Some(tag_token) = self.current_tag_token.clone() {
+        self.drop_current_attribute_if_duplicate();
+        if let Some(Tag::EndTag { attributes, self_closing, .. }) = &self.current_tag_token {
+            // end tags can't have attributes or be self-closing, both are dropped by the tree
+            // construction stage
+            let (has_attributes, self_closing) = (!attributes.is_empty(), *self_closing);
+            if has_attributes {
+                self.emit_parse_error(ParseError::EndTagWithAttributes);
+            }
+            if self_closing {
+                self.emit_parse_error(ParseError::EndTagWithTrailingSolidus);
+            }
+        }
+        let span = self.current_token_span();
+        if let Some(
+            Tag::StartTag { span: tag_span, .. } | Tag::EndTag { span: tag_span, .. },
+        ) = &mut self.current_tag_token
+        {
+            *tag_span = span;
+        }
+        if let Some(tag_token) = self.current_tag_token.take() {
+            // remember the name of the start tag, it's needed to tell whether an end tag in the
+            // RCDATA, RAWTEXT and script data states is an appropriate end tag token
+            if let Tag::StartTag { tag_name, .. } = &tag_token {
+                self.last_start_tag_name = Some(tag_name.clone());
+            }
             self.emit_token(HTMLToken::Tag(tag_token));
         }
         // clear the current start tag token
         self.current_tag_token = None;
     }
 
+    fn append_to_current_comment(&mut self, character: char) {
+        if let Some(comment_token) = &mut self.current_comment_token {
+            push_to_text(
+                &self.input,
+                &mut comment_token.data,
+                character,
+                self.current_position.offset,
+            );
+        }
+    }
+
+    /// What the comment states do at the end of the input: the comment is emitted with whatever
+    /// data it has so far. The end-of-file token still has to be emitted after this
+    fn eof_in_comment(&mut self) {
+        self.emit_parse_error(ParseError::EofInComment);
+        self.emit_current_comment_token();
+    }
+
     fn emit_current_comment_token(&mut self) {
-        if let Some(comment_token) = self.current_comment_token.clone() {
+        let span = self.current_token_span();
+        if let Some(mut comment_token) = self.current_comment_token.take() {
+            comment_token.span = span;
             self.emit_token(HTMLToken::Comment(comment_token));
         }
         // clear the current comment token
         self.current_comment_token = None;
     }
 
+    /// The after DOCTYPE public keyword and after DOCTYPE system keyword states only differ in
+    /// which identifier they're about to read. This is called after the current input character
+    /// has been consumed
+    fn after_doctype_keyword_state(&mut self, identifier: DoctypeIdentifier) {
+        match self.current_input_character {
+            Some('\t' | '\n' | '\x0C' | ' ') => {
+                self.switch_state(identifier.before_state());
+            }
+            Some(quote @ ('"' | '\'')) => {
+                self.emit_parse_error(identifier.missing_whitespace_after_keyword_error());
+                self.start_doctype_identifier(identifier, quote);
+            }
+            _ => self.before_doctype_identifier_state(identifier),
+        }
+    }
+
+    /// The before DOCTYPE public identifier and before DOCTYPE system identifier states. This is
+    /// called after the current input character has been consumed
+    fn before_doctype_identifier_state(&mut self, identifier: DoctypeIdentifier) {
+        match self.current_input_character {
+            Some('\t' | '\n' | '\x0C' | ' ') => {
+                // ignore the character
+            }
+            Some(quote @ ('"' | '\'')) => {
+                self.start_doctype_identifier(identifier, quote);
+            }
+            Some('>') => {
+                self.emit_parse_error(identifier.missing_identifier_error());
+                self.set_current_doctype_force_quirks();
+                self.switch_state(HTMLTokenizerState::Data);
+                self.emit_current_doctype_token();
+            }
+            _ => {
+                self.emit_parse_error(identifier.missing_quote_error());
+                self.set_current_doctype_force_quirks();
+                self.switch_state(HTMLTokenizerState::BogusDOCTYPE);
+                self.reconsume = true;
+            }
+        }
+    }
+
+    /// The four DOCTYPE identifier (double-quoted and single-quoted) states, which read the
+    /// identifier up to the closing `quote`. This is called after the current input character
+    /// has been consumed
+    fn doctype_identifier_quoted_state(&mut self, identifier: DoctypeIdentifier, quote: char) {
+        match self.current_input_character {
+            Some(current_input_character) if current_input_character == quote => {
+                self.switch_state(identifier.after_state());
+            }
+            Some('\0') => {
+                self.emit_parse_error(ParseError::UnexpectedNullCharacter);
+                self.append_to_doctype_identifier(identifier, char::REPLACEMENT_CHARACTER);
+            }
+            Some('>') => {
+                self.emit_parse_error(identifier.abrupt_identifier_error());
+                self.set_current_doctype_force_quirks();
+                self.switch_state(HTMLTokenizerState::Data);
+                self.emit_current_doctype_token();
+            }
+            Some(current_input_character) => {
+                self.append_to_doctype_identifier(identifier, current_input_character);
+            }
+            None => {}
+        }
+    }
+
+    /// Sets the identifier to the empty string (not missing) and switches to the state that
+    /// reads it up to the matching `quote`
+    fn start_doctype_identifier(&mut self, identifier: DoctypeIdentifier, quote: char) {
+        if let Some(doctype_token) = &mut self.current_doctype_token {
+            match identifier {
+                DoctypeIdentifier::Public => doctype_token.public_identifier = Some(Cow::Borrowed("")),
+                DoctypeIdentifier::System => doctype_token.system_identifier = Some(Cow::Borrowed("")),
+            }
+        }
+        self.switch_state(identifier.quoted_state(quote));
+    }
+
+    fn append_to_doctype_identifier(&mut self, identifier: DoctypeIdentifier, character: char) {
+        if let Some(doctype_token) = &mut self.current_doctype_token {
+            let value = match identifier {
+                DoctypeIdentifier::Public => &mut doctype_token.public_identifier,
+                DoctypeIdentifier::System => &mut doctype_token.system_identifier,
+            };
+            let value = value.get_or_insert(Cow::Borrowed(""));
+            push_to_text(&self.input, value, character, self.current_position.offset);
+        }
+    }
+
+    fn set_current_doctype_force_quirks(&mut self) {
+        if let Some(doctype_token) = &mut self.current_doctype_token {
+            doctype_token.force_quirks = true;
+        }
+    }
+
+    /// What every DOCTYPE state but the bogus one does at the end of the input: the doctype is
+    /// emitted with its force-quirks flag set. The end-of-file token still has to be emitted after
+    /// this
+    fn eof_in_doctype(&mut self) {
+        self.emit_parse_error(ParseError::EofInDoctype);
+        self.set_current_doctype_force_quirks();
+        self.emit_current_doctype_token();
+    }
+
     fn emit_current_doctype_token(&mut self) {
-        if let Some(doctype_token) = self.current_doctype_token.clone() {
+        let span = self.current_token_span();
+        if let Some(mut doctype_token) = self.current_doctype_token.take() {
+            doctype_token.span = span;
             self.emit_token(HTMLToken::Doctype(doctype_token));
         }
         // clear the current doctype token
@@ -720,32 +2783,167 @@ impl HTMLTokenizer {
     }
 
     fn consume_substring(&mut self, substring: String) {
-        // move the cursor forward by the length of the substring
-        for _ in 0..substring.len() {
+        for _ in substring.chars() {
             self.consume_next_input_character();
         }
     }
 
+    /// The input from the next input character on, as it is in the source
+    fn remaining_input(&self) -> &str {
+        &self.input[self.next_position.offset..]
+    }
+
+    /// Like `next_few_characters_are`, but the case has to match too
+    fn next_few_characters_are_exactly(&self, substring: &str) -> bool {
+        self.remaining_input().starts_with(substring)
+    }
+
+    /// Whether the input continues with `substring`, ignoring ASCII case
     fn next_few_characters_are(&self, substring: String) -> bool {
-        let mut start_index = self.cursor.unwrap().clone();
-        for char in substring.chars() {
-            if let Some(char_from_input_stream) = self.input_stream.get(start_index) {
-                // case-insensitive comparison
-                if &char_from_input_stream.to_lowercase().to_string()
-                    != &char.to_lowercase().to_string()
-                {
-                    return false;
-                }
-                start_index += 1;
-            } else {
-                panic!("reached end of file");
+        self.remaining_input()
+            .as_bytes()
+            .get(..substring.len())
+            .is_some_and(|next_few_bytes| next_few_bytes.eq_ignore_ascii_case(substring.as_bytes()))
+    }
+}
+
+impl<'a> Iterator for HTMLTokenizer<'a> {
+    type Item = HTMLToken<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_token()
+    }
+}
+
+/// The character at `offset` in the input, with newlines normalized the way the spec's input
+/// stream preprocessing does: a CR on its own, or followed by an LF, is read as an LF. Callers skip
+/// over the LF of a CR LF pair themselves
+fn input_character_at(input: &str, offset: usize) -> Option<char> {
+    match input[offset..].chars().next()? {
+        '\r' => Some('\n'),
+        character => Some(character),
+    }
+}
+
+/// Appends `character` to a token's `text`, see `push_str_to_text`
+#[allow(clippy::ptr_arg)]
+fn push_to_text<'a>(input: &Cow<'a, str>, text: &mut Cow<'a, str>, character: char, anchor: usize) {
+    push_str_to_text(input, text, character.encode_utf8(&mut [0; 4]), anchor);
+}
+
+/// Appends `string` to a token's `text`, which stays a slice of the input for as long as it's an
+/// exact copy of part of it. `anchor` is where in the input an empty text would start. Anything
+/// that doesn't match the input (a lowercased letter, a decoded character reference, a normalized
+/// newline...) turns the text into an owned copy
+// the input has to be a Cow, text can only stay borrowed from an input that is borrowed itself
+#[allow(clippy::ptr_arg)]
+fn push_str_to_text<'a>(input: &Cow<'a, str>, text: &mut Cow<'a, str>, string: &str, anchor: usize) {
+    if let Cow::Borrowed(input) = *input {
+        // where the text is in the input, if it's borrowed from it at all
+        let start = match *text {
+            _ if text.is_empty() => Some(anchor),
+            Cow::Borrowed(borrowed) => (borrowed.as_ptr() as usize)
+                .checked_sub(input.as_ptr() as usize)
+                .filter(|start| start + borrowed.len() <= input.len()),
+            Cow::Owned(_) => None,
+        };
+        if let Some(start) = start {
+            let end = start + text.len();
+            if input[end..].starts_with(string) {
+                *text = Cow::Borrowed(&input[start..end + string.len()]);
+                return;
             }
         }
-        return true;
     }
+    text.to_mut().push_str(string);
 }
 
-#[derive(Debug)]
+/// Bytes the fast path leaves for the slow path to consume, because there's more to do with them
+/// than pass them along: NUL, the control characters that are reported as parse errors, and the
+/// first byte of any UTF-8 sequence that could be a C1 control character or a noncharacter
+fn needs_a_closer_look(byte: u8) -> bool {
+    match byte {
+        b'\t' | b'\n' | b'\x0C' => false,
+        0x00..=0x1F | 0x7F => true,
+        0xC2 | 0xEF | 0xF0..=0xF4 => true,
+        _ => false,
+    }
+}
+
+fn is_noncharacter(code: u32) -> bool {
+    matches!(code, 0xFDD0..=0xFDEF) || (code & 0xFFFE) == 0xFFFE
+}
+
+fn is_control(code: u32) -> bool {
+    matches!(code, 0x00..=0x1F | 0x7F..=0x9F)
+}
+
+fn is_ascii_whitespace(code: u32) -> bool {
+    matches!(code, 0x09 | 0x0A | 0x0C | 0x0D | 0x20)
+}
+
+/// Which of the two DOCTYPE identifiers a state is reading. The states for the public and the
+/// system identifier work the same way, so they share their code and use this to tell them apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DoctypeIdentifier {
+    Public,
+    System,
+}
+
+impl DoctypeIdentifier {
+    fn before_state(self) -> HTMLTokenizerState {
+        match self {
+            DoctypeIdentifier::Public => HTMLTokenizerState::BeforeDOCTYPEPublicIdentifier,
+            DoctypeIdentifier::System => HTMLTokenizerState::BeforeDOCTYPESystemIdentifier,
+        }
+    }
+
+    fn quoted_state(self, quote: char) -> HTMLTokenizerState {
+        match (self, quote) {
+            (DoctypeIdentifier::Public, '"') => HTMLTokenizerState::DOCTYPEPublicIdentifierDoubleQuoted,
+            (DoctypeIdentifier::Public, _) => HTMLTokenizerState::DOCTYPEPublicIdentifierSingleQuoted,
+            (DoctypeIdentifier::System, '"') => HTMLTokenizerState::DOCTYPESystemIdentifierDoubleQuoted,
+            (DoctypeIdentifier::System, _) => HTMLTokenizerState::DOCTYPESystemIdentifierSingleQuoted,
+        }
+    }
+
+    fn after_state(self) -> HTMLTokenizerState {
+        match self {
+            DoctypeIdentifier::Public => HTMLTokenizerState::AfterDOCTYPEPublicIdentifier,
+            DoctypeIdentifier::System => HTMLTokenizerState::AfterDOCTYPESystemIdentifier,
+        }
+    }
+
+    fn missing_whitespace_after_keyword_error(self) -> ParseError {
+        match self {
+            DoctypeIdentifier::Public => ParseError::MissingWhitespaceAfterDoctypePublicKeyword,
+            DoctypeIdentifier::System => ParseError::MissingWhitespaceAfterDoctypeSystemKeyword,
+        }
+    }
+
+    fn missing_identifier_error(self) -> ParseError {
+        match self {
+            DoctypeIdentifier::Public => ParseError::MissingDoctypePublicIdentifier,
+            DoctypeIdentifier::System => ParseError::MissingDoctypeSystemIdentifier,
+        }
+    }
+
+    fn missing_quote_error(self) -> ParseError {
+        match self {
+            DoctypeIdentifier::Public => ParseError::MissingQuoteBeforeDoctypePublicIdentifier,
+            DoctypeIdentifier::System => ParseError::MissingQuoteBeforeDoctypeSystemIdentifier,
+        }
+    }
+
+    fn abrupt_identifier_error(self) -> ParseError {
+        match self {
+            DoctypeIdentifier::Public => ParseError::AbruptDoctypePublicIdentifier,
+            DoctypeIdentifier::System => ParseError::AbruptDoctypeSystemIdentifier,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum HTMLTokenizerState {
     Data,
     RCDATA,
@@ -828,3 +3026,51 @@ pub enum HTMLTokenizerState {
     DecimalCharacterReference,
     NumericCharacterReferenceEnd,
 }
+
+impl HTMLTokenizerState {
+    /// The states that read the text between tokens, where every character consumed is either
+    /// text or the start of some markup
+    fn is_text_state(self) -> bool {
+        matches!(
+            self,
+            HTMLTokenizerState::Data
+                | HTMLTokenizerState::RCDATA
+                | HTMLTokenizerState::RAWTEXT
+                | HTMLTokenizerState::ScriptData
+                | HTMLTokenizerState::PLAINTEXT
+                | HTMLTokenizerState::ScriptDataEscaped
+                | HTMLTokenizerState::ScriptDataEscapedDash
+                | HTMLTokenizerState::ScriptDataEscapedDashDash
+                | HTMLTokenizerState::ScriptDataDoubleEscaped
+                | HTMLTokenizerState::ScriptDataDoubleEscapedDash
+                | HTMLTokenizerState::ScriptDataDoubleEscapedDashDash
+                | HTMLTokenizerState::CDATASection
+        )
+    }
+}
+
+/// The states a tokenizer can be started in from outside, the ones the tree construction stage
+/// switches to when it sees an element like `<title>` or `<script>`. Every other state is only
+/// ever entered part way through a token, and relies on the tokenizer having set it up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitialState {
+    Data,
+    RCDATA,
+    RAWTEXT,
+    ScriptData,
+    PLAINTEXT,
+    CDATASection,
+}
+
+impl From<InitialState> for HTMLTokenizerState {
+    fn from(state: InitialState) -> Self {
+        match state {
+            InitialState::Data => HTMLTokenizerState::Data,
+            InitialState::RCDATA => HTMLTokenizerState::RCDATA,
+            InitialState::RAWTEXT => HTMLTokenizerState::RAWTEXT,
+            InitialState::ScriptData => HTMLTokenizerState::ScriptData,
+            InitialState::PLAINTEXT => HTMLTokenizerState::PLAINTEXT,
+            InitialState::CDATASection => HTMLTokenizerState::CDATASection,
+        }
+    }
+}
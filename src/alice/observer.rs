@@ -0,0 +1,64 @@
+use super::{HTMLToken, HTMLTokenizerState, ParseErrorReport};
+
+/// Gets told what the tokenizer is doing as it runs. Every method does nothing by default, so an
+/// observer only has to implement the ones it cares about.
+///
+/// Nothing is observed unless an observer is set with `HTMLTokenizer::set_observer`, and the
+/// tokenizer itself never writes to stdout or stderr.
+pub trait TokenizerObserver {
+    /// Called every time the tokenizer switches state, including when the tree construction stage
+    /// switches it
+    fn state_switched(&mut self, _from: HTMLTokenizerState, _to: HTMLTokenizerState) {}
+
+    /// Called when the current input character is handed out again instead of advancing the
+    /// cursor
+    fn character_reconsumed(
+        &mut self,
+        _state: HTMLTokenizerState,
+        _current_input_character: Option<char>,
+        _next_input_character: Option<char>,
+    ) {
+    }
+
+    /// Called after a character is appended to the current DOCTYPE token's name in the DOCTYPE
+    /// name state, with the name so far
+    fn doctype_name_updated(&mut self, _name: &str) {}
+
+    fn token_emitted(&mut self, _token: &HTMLToken<'_>) {}
+
+    fn parse_error(&mut self, _report: &ParseErrorReport) {}
+
+    fn end_of_file_reached(&mut self) {}
+}
+
+/// Prints what the tokenizer is doing to stdout. This is the debug output the tokenizer used to
+/// print on its own, for when you want to follow along with the state machine
+#[derive(Debug, Default)]
+pub struct DebugTracer;
+
+impl TokenizerObserver for DebugTracer {
+    fn character_reconsumed(
+        &mut self,
+        state: HTMLTokenizerState,
+        current_input_character: Option<char>,
+        next_input_character: Option<char>,
+    ) {
+        println!("reconsume @ {:?}", state);
+        println!(
+            "c_i_c and n_i_c : {:?} {:?}",
+            current_input_character, next_input_character
+        );
+    }
+
+    fn doctype_name_updated(&mut self, name: &str) {
+        println!("name: {}", name);
+    }
+
+    fn token_emitted(&mut self, token: &HTMLToken<'_>) {
+        println!("token: {:?}", token);
+    }
+
+    fn end_of_file_reached(&mut self) {
+        println!("{:?}", "end of file reached");
+    }
+}
@@ -0,0 +1,242 @@
+use std::fmt;
+use std::ops::Range;
+
+/// Where something happened in the input, used to point parse errors back at the html source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourcePosition {
+    pub line: usize,   // 1-based
+    pub column: usize, // 1-based, counted in characters rather than bytes
+    pub offset: usize, // 0-based byte offset into the input
+}
+
+impl Default for SourcePosition {
+    fn default() -> Self {
+        Self {
+            line: 1,
+            column: 1,
+            offset: 0,
+        }
+    }
+}
+
+impl SourcePosition {
+    /// Returns the position right after `character`, given that it is found at this position
+    pub fn advance(self, character: char) -> Self {
+        if character == '\n' {
+            Self {
+                line: self.line + 1,
+                column: 1,
+                offset: self.offset + character.len_utf8(),
+            }
+        } else {
+            Self {
+                line: self.line,
+                column: self.column + 1,
+                offset: self.offset + character.len_utf8(),
+            }
+        }
+    }
+
+    /// Returns the position right after `text`, given that it starts at this position. Same as
+    /// calling `advance` for each of its characters, in one go
+    pub fn advance_over(self, text: &str) -> Self {
+        let bytes = text.as_bytes();
+        let offset = self.offset + bytes.len();
+        match memchr::memrchr(b'\n', bytes) {
+            Some(last_line_feed) => Self {
+                line: self.line + memchr::memchr_iter(b'\n', bytes).count(),
+                column: 1 + text[last_line_feed + 1..].chars().count(),
+                offset,
+            },
+            None => Self {
+                line: self.line,
+                column: self.column + text.chars().count(),
+                offset,
+            },
+        }
+    }
+}
+
+impl fmt::Display for SourcePosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// The stretch of the input a token (or part of one) was read from. `start` is where its first
+/// character is and `end` is right after its last one, so an empty span has `start == end`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SourceSpan {
+    pub start: SourcePosition,
+    pub end: SourcePosition,
+}
+
+impl SourceSpan {
+    /// The byte offsets of the span, for slicing the original input
+    pub fn byte_range(&self) -> Range<usize> {
+        self.start.offset..self.end.offset
+    }
+}
+
+impl fmt::Display for SourceSpan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+/// The parse errors the tokenizer can run into. Each variant matches one of the error codes in
+/// https://html.spec.whatwg.org/multipage/parsing.html#parse-errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseError {
+    AbruptClosingOfEmptyComment,
+    AbruptDoctypePublicIdentifier,
+    AbruptDoctypeSystemIdentifier,
+    AbsenceOfDigitsInNumericCharacterReference,
+    CdataInHtmlContent,
+    CharacterReferenceOutsideUnicodeRange,
+    ControlCharacterInInputStream,
+    ControlCharacterReference,
+    DuplicateAttribute,
+    EndTagWithAttributes,
+    EndTagWithTrailingSolidus,
+    EofBeforeTagName,
+    EofInCdata,
+    EofInComment,
+    EofInDoctype,
+    EofInScriptHtmlCommentLikeText,
+    EofInTag,
+    IncorrectlyClosedComment,
+    IncorrectlyOpenedComment,
+    InvalidCharacterSequenceAfterDoctypeName,
+    InvalidFirstCharacterOfTagName,
+    MissingAttributeValue,
+    MissingDoctypeName,
+    MissingDoctypePublicIdentifier,
+    MissingDoctypeSystemIdentifier,
+    MissingEndTagName,
+    MissingQuoteBeforeDoctypePublicIdentifier,
+    MissingQuoteBeforeDoctypeSystemIdentifier,
+    MissingSemicolonAfterCharacterReference,
+    MissingWhitespaceAfterDoctypePublicKeyword,
+    MissingWhitespaceAfterDoctypeSystemKeyword,
+    MissingWhitespaceBeforeDoctypeName,
+    MissingWhitespaceBetweenAttributes,
+    MissingWhitespaceBetweenDoctypePublicAndSystemIdentifiers,
+    NestedComment,
+    NoncharacterCharacterReference,
+    NoncharacterInInputStream,
+    NullCharacterReference,
+    SurrogateCharacterReference,
+    SurrogateInInputStream,
+    UnexpectedCharacterAfterDoctypeSystemIdentifier,
+    UnexpectedCharacterInAttributeName,
+    UnexpectedCharacterInUnquotedAttributeValue,
+    UnexpectedEqualsSignBeforeAttributeName,
+    UnexpectedNullCharacter,
+    UnexpectedQuestionMarkInsteadOfTagName,
+    UnexpectedSolidusInTag,
+    UnknownNamedCharacterReference,
+}
+
+impl ParseError {
+    /// The error code as it is spelled in the spec, e.g. "unexpected-null-character"
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseError::AbruptClosingOfEmptyComment => "abrupt-closing-of-empty-comment",
+            ParseError::AbruptDoctypePublicIdentifier => "abrupt-doctype-public-identifier",
+            ParseError::AbruptDoctypeSystemIdentifier => "abrupt-doctype-system-identifier",
+            ParseError::AbsenceOfDigitsInNumericCharacterReference => {
+                "absence-of-digits-in-numeric-character-reference"
+            }
+            ParseError::CdataInHtmlContent => "cdata-in-html-content",
+            ParseError::CharacterReferenceOutsideUnicodeRange => {
+                "character-reference-outside-unicode-range"
+            }
+            ParseError::ControlCharacterInInputStream => "control-character-in-input-stream",
+            ParseError::ControlCharacterReference => "control-character-reference",
+            ParseError::DuplicateAttribute => "duplicate-attribute",
+            ParseError::EndTagWithAttributes => "end-tag-with-attributes",
+            ParseError::EndTagWithTrailingSolidus => "end-tag-with-trailing-solidus",
+            ParseError::EofBeforeTagName => "eof-before-tag-name",
+            ParseError::EofInCdata => "eof-in-cdata",
+            ParseError::EofInComment => "eof-in-comment",
+            ParseError::EofInDoctype => "eof-in-doctype",
+            ParseError::EofInScriptHtmlCommentLikeText => "eof-in-script-html-comment-like-text",
+            ParseError::EofInTag => "eof-in-tag",
+            ParseError::IncorrectlyClosedComment => "incorrectly-closed-comment",
+            ParseError::IncorrectlyOpenedComment => "incorrectly-opened-comment",
+            ParseError::InvalidCharacterSequenceAfterDoctypeName => {
+                "invalid-character-sequence-after-doctype-name"
+            }
+            ParseError::InvalidFirstCharacterOfTagName => "invalid-first-character-of-tag-name",
+            ParseError::MissingAttributeValue => "missing-attribute-value",
+            ParseError::MissingDoctypeName => "missing-doctype-name",
+            ParseError::MissingDoctypePublicIdentifier => "missing-doctype-public-identifier",
+            ParseError::MissingDoctypeSystemIdentifier => "missing-doctype-system-identifier",
+            ParseError::MissingEndTagName => "missing-end-tag-name",
+            ParseError::MissingQuoteBeforeDoctypePublicIdentifier => {
+                "missing-quote-before-doctype-public-identifier"
+            }
+            ParseError::MissingQuoteBeforeDoctypeSystemIdentifier => {
+                "missing-quote-before-doctype-system-identifier"
+            }
+            ParseError::MissingSemicolonAfterCharacterReference => {
+                "missing-semicolon-after-character-reference"
+            }
+            ParseError::MissingWhitespaceAfterDoctypePublicKeyword => {
+                "missing-whitespace-after-doctype-public-keyword"
+            }
+            ParseError::MissingWhitespaceAfterDoctypeSystemKeyword => {
+                "missing-whitespace-after-doctype-system-keyword"
+            }
+            ParseError::MissingWhitespaceBeforeDoctypeName => "missing-whitespace-before-doctype-name",
+            ParseError::MissingWhitespaceBetweenAttributes => "missing-whitespace-between-attributes",
+            ParseError::MissingWhitespaceBetweenDoctypePublicAndSystemIdentifiers => {
+                "missing-whitespace-between-doctype-public-and-system-identifiers"
+            }
+            ParseError::NestedComment => "nested-comment",
+            ParseError::NoncharacterCharacterReference => "noncharacter-character-reference",
+            ParseError::NoncharacterInInputStream => "noncharacter-in-input-stream",
+            ParseError::NullCharacterReference => "null-character-reference",
+            ParseError::SurrogateCharacterReference => "surrogate-character-reference",
+            ParseError::SurrogateInInputStream => "surrogate-in-input-stream",
+            ParseError::UnexpectedCharacterAfterDoctypeSystemIdentifier => {
+                "unexpected-character-after-doctype-system-identifier"
+            }
+            ParseError::UnexpectedCharacterInAttributeName => {
+                "unexpected-character-in-attribute-name"
+            }
+            ParseError::UnexpectedCharacterInUnquotedAttributeValue => {
+                "unexpected-character-in-unquoted-attribute-value"
+            }
+            ParseError::UnexpectedEqualsSignBeforeAttributeName => {
+                "unexpected-equals-sign-before-attribute-name"
+            }
+            ParseError::UnexpectedNullCharacter => "unexpected-null-character",
+            ParseError::UnexpectedQuestionMarkInsteadOfTagName => {
+                "unexpected-question-mark-instead-of-tag-name"
+            }
+            ParseError::UnexpectedSolidusInTag => "unexpected-solidus-in-tag",
+            ParseError::UnknownNamedCharacterReference => "unknown-named-character-reference",
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+/// A parse error together with the position of the input character that caused it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseErrorReport {
+    pub error: ParseError,
+    pub position: SourcePosition,
+}
+
+impl fmt::Display for ParseErrorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.position, self.error)
+    }
+}
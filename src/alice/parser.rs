@@ -0,0 +1,2574 @@
+use std::borrow::Cow;
+
+use super::foreign::{
+    adjust_foreign_attributes, adjust_mathml_attributes, adjust_svg_attributes, adjust_svg_tag_name,
+};
+use super::quirks::quirks_mode;
+use super::{Attributes, Character, Comment, HTMLToken, HTMLTokenizer, HTMLTokenizerState, Tag};
+use crate::dom::{Document, Element, Namespace, NodeId, NodeKind, QuirksMode};
+
+/// The tree construction stage. It takes the tokens coming out of the tokenizer and builds a
+/// `Document` out of them, following the insertion modes from
+/// https://html.spec.whatwg.org/multipage/parsing.html#tree-construction
+pub struct HTMLParser<'a> {
+    tokenizer: HTMLTokenizer<'a>,
+    document: Document,
+    insertion_mode: InsertionMode,
+    original_insertion_mode: Option<InsertionMode>,
+    stack_of_open_elements: Vec<NodeId>,
+    list_of_active_formatting_elements: Vec<ActiveFormattingElement>,
+    head_element_pointer: Option<NodeId>,
+    form_element_pointer: Option<NodeId>,
+    frameset_ok: bool,
+    ignore_next_line_feed: bool, // set after <textarea>, whose first newline isn't part of its value
+    foster_parenting: bool,
+    pending_table_character_tokens: String, // text collected in the "in table text" insertion mode
+    stack_of_template_insertion_modes: Vec<InsertionMode>,
+    stopped: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsertionMode {
+    Initial,
+    BeforeHtml,
+    BeforeHead,
+    InHead,
+    InHeadNoscript,
+    AfterHead,
+    InBody,
+    Text,
+    InTable,
+    InTableText,
+    InCaption,
+    InColumnGroup,
+    InTableBody,
+    InRow,
+    InCell,
+    InSelect,
+    InSelectInTable,
+    InTemplate,
+    AfterBody,
+    InFrameset,
+    AfterFrameset,
+    AfterAfterBody,
+    AfterAfterFrameset,
+}
+
+#[derive(Debug)]
+enum ActiveFormattingElement {
+    Marker,
+    Element(NodeId),
+}
+
+/// Where a new node should go: inside `parent`, right before `before` (or as the last child if
+/// there's nothing to insert before)
+struct InsertionLocation {
+    parent: NodeId,
+    before: Option<NodeId>,
+}
+
+impl<'a> HTMLParser<'a> {
+    pub fn new(mut tokenizer: HTMLTokenizer<'a>) -> Self {
+        // most insertion modes treat whitespace differently from other characters, so every
+        // Character token should be one or the other
+        tokenizer.set_split_whitespace_runs(true);
+        Self {
+            tokenizer,
+            document: Document::new(),
+            insertion_mode: InsertionMode::Initial,
+            original_insertion_mode: None,
+            stack_of_open_elements: Vec::new(),
+            list_of_active_formatting_elements: Vec::new(),
+            head_element_pointer: None,
+            form_element_pointer: None,
+            frameset_ok: true,
+            ignore_next_line_feed: false,
+            foster_parenting: false,
+            pending_table_character_tokens: String::new(),
+            stack_of_template_insertion_modes: Vec::new(),
+            stopped: false,
+        }
+    }
+
+    /// Runs the tokenizer to the end of the input and returns the document built from it
+    pub fn parse(mut self) -> Document {
+        while !self.stopped {
+            match self.tokenizer.next_token() {
+                Some(token) => self.process_token(token),
+                None => break,
+            }
+        }
+        self.document
+    }
+
+    fn process_token(&mut self, token: HTMLToken<'a>) {
+        let token = if self.ignore_next_line_feed {
+            self.ignore_next_line_feed = false;
+            match token {
+                HTMLToken::Character(Character { data, span }) if data.starts_with('\n') => {
+                    let data = match data {
+                        Cow::Borrowed(data) => Cow::Borrowed(&data[1..]),
+                        Cow::Owned(data) => Cow::Owned(data[1..].to_string()),
+                    };
+                    if data.is_empty() {
+                        return;
+                    }
+                    HTMLToken::Character(Character { data, span })
+                }
+                token => token,
+            }
+        } else {
+            token
+        };
+
+        if self.is_processed_as_html_content(&token) {
+            self.reprocess_token(token);
+        } else {
+            self.process_token_in_foreign_content(token);
+        }
+
+        // the tokenizer only recognizes CDATA sections in foreign content
+        let in_foreign_content = self
+            .adjusted_current_node()
+            .is_some_and(|node| !self.is_html_element(node));
+        self.tokenizer.set_in_foreign_content(in_foreign_content);
+    }
+
+    /// Whether a token goes through the current insertion mode (the tree construction
+    /// dispatcher). Inside svg and math elements it doesn't, other than at the points where HTML
+    /// can be nested in them, like `<foreignObject>`
+    fn is_processed_as_html_content(&self, token: &HTMLToken<'a>) -> bool {
+        let Some(node) = self.adjusted_current_node() else {
+            return true;
+        };
+        if self.is_html_element(node) {
+            return true;
+        }
+        let is_start_tag = matches!(token, HTMLToken::Tag(Tag::StartTag { .. }));
+        let is_character = matches!(token, HTMLToken::Character(_));
+        if self.is_one_of(node, MATHML_TEXT_INTEGRATION_POINTS) {
+            match token {
+                HTMLToken::Tag(Tag::StartTag { tag_name, .. })
+                    if !matches!(tag_name.as_ref(), "mglyph" | "malignmark") =>
+                {
+                    return true;
+                }
+                HTMLToken::Character(_) => return true,
+                _ => {}
+            }
+        }
+        if let HTMLToken::Tag(Tag::StartTag { tag_name, .. }) = token {
+            if tag_name == "svg" && self.is_one_of(node, &[(Namespace::MathML, "annotation-xml")]) {
+                return true;
+            }
+        }
+        if (is_start_tag || is_character) && self.is_html_integration_point(node) {
+            return true;
+        }
+        matches!(token, HTMLToken::EndOfFile)
+    }
+
+    /// The current node. The spec uses the context element instead when parsing a fragment,
+    /// which this parser doesn't do
+    fn adjusted_current_node(&self) -> Option<NodeId> {
+        self.stack_of_open_elements.last().copied()
+    }
+
+    fn is_html_element(&self, node: NodeId) -> bool {
+        self.document
+            .node(node)
+            .as_element()
+            .is_some_and(|element| element.is_html())
+    }
+
+    /// The svg and math elements that can have HTML inside them
+    fn is_html_integration_point(&self, node: NodeId) -> bool {
+        let Some(element) = self.document.node(node).as_element() else {
+            return false;
+        };
+        match element.namespace {
+            Namespace::MathML => {
+                element.name == "annotation-xml"
+                    && element.get_attribute("encoding").is_some_and(|encoding| {
+                        encoding.eq_ignore_ascii_case("text/html")
+                            || encoding.eq_ignore_ascii_case("application/xhtml+xml")
+                    })
+            }
+            Namespace::Svg => matches!(element.name.as_str(), "foreignObject" | "desc" | "title"),
+            _ => false,
+        }
+    }
+
+    // The rules for parsing tokens in foreign content
+    fn process_token_in_foreign_content(&mut self, token: HTMLToken<'a>) {
+        match token {
+            HTMLToken::Character(Character { ref data, .. }) => {
+                // null characters are a parse error, they're replaced rather than dropped. Only
+                // the other characters that aren't whitespace make a frameset not ok though
+                let is_content = |character| character != '\0' && !is_whitespace_character(character);
+                if data.chars().any(is_content) {
+                    self.frameset_ok = false;
+                }
+                self.insert_characters(&data.replace('\0', "\u{FFFD}"));
+            }
+            HTMLToken::Comment(Comment { data, .. }) => self.insert_comment(data, None),
+            HTMLToken::Doctype(_) => {
+                // parse error. ignore the token
+            }
+            HTMLToken::Tag(Tag::StartTag {
+                ref tag_name,
+                ref attributes,
+                ..
+            }) if breaks_out_of_foreign_content(tag_name)
+                || (tag_name == "font"
+                    && attributes
+                        .iter()
+                        .any(|attribute| {
+                            matches!(attribute.key.as_ref(), "color" | "face" | "size")
+                        })) =>
+            {
+                self.break_out_of_foreign_content(token);
+            }
+            HTMLToken::Tag(Tag::EndTag { ref tag_name, .. })
+                if matches!(tag_name.as_ref(), "br" | "p") =>
+            {
+                self.break_out_of_foreign_content(token);
+            }
+            HTMLToken::Tag(Tag::StartTag {
+                ref tag_name,
+                ref attributes,
+                self_closing,
+                ..
+            }) => {
+                let namespace = self
+                    .adjusted_current_node()
+                    .and_then(|node| self.document.node(node).as_element())
+                    .map_or(Namespace::Html, |element| element.namespace);
+                let mut attributes = attributes.clone();
+                let tag_name = match namespace {
+                    Namespace::MathML => {
+                        adjust_mathml_attributes(&mut attributes);
+                        tag_name.as_ref()
+                    }
+                    Namespace::Svg => {
+                        adjust_svg_attributes(&mut attributes);
+                        adjust_svg_tag_name(tag_name)
+                    }
+                    _ => tag_name.as_ref(),
+                };
+                adjust_foreign_attributes(&mut attributes);
+                self.insert_foreign_element(tag_name, namespace, attributes);
+                if self_closing {
+                    // scripts aren't run, so a self-closing svg script is popped like anything
+                    // else
+                    self.stack_of_open_elements.pop();
+                }
+            }
+            HTMLToken::Tag(Tag::EndTag { .. }) => self.any_other_end_tag_in_foreign_content(token),
+            HTMLToken::EndOfFile => {
+                // the dispatcher never sends the end-of-file token here
+            }
+        }
+    }
+
+    /// HTML that can't be in svg or math, like a `<p>`, closes the foreign elements it's in
+    /// (parse error) and is processed as HTML
+    fn break_out_of_foreign_content(&mut self, token: HTMLToken<'a>) {
+        loop {
+            let current_node = self.current_node();
+            if self.is_one_of(current_node, MATHML_TEXT_INTEGRATION_POINTS)
+                || self.is_html_integration_point(current_node)
+                || self.is_html_element(current_node)
+            {
+                break;
+            }
+            self.stack_of_open_elements.pop();
+        }
+        self.reprocess_token(token);
+    }
+
+    /// End tags in foreign content close the element with the same name, compared ignoring case
+    /// since svg has names like `foreignObject`. An HTML element found on the way down the stack
+    /// hands the end tag over to the current insertion mode
+    fn any_other_end_tag_in_foreign_content(&mut self, token: HTMLToken<'a>) {
+        let HTMLToken::Tag(Tag::EndTag { ref tag_name, .. }) = token else {
+            return;
+        };
+        // if the current node's tag name isn't tag_name, this is a parse error
+        for index in (1..self.stack_of_open_elements.len()).rev() {
+            let node = self.stack_of_open_elements[index];
+            if index != self.stack_of_open_elements.len() - 1 && self.is_html_element(node) {
+                self.reprocess_token(token);
+                return;
+            }
+            let is_match = self
+                .document
+                .node(node)
+                .element_name()
+                .is_some_and(|name| name.eq_ignore_ascii_case(tag_name));
+            if is_match {
+                self.stack_of_open_elements.truncate(index);
+                return;
+            }
+        }
+    }
+
+    /// Processes the token using the rules for the current insertion mode
+    fn reprocess_token(&mut self, token: HTMLToken<'a>) {
+        self.process_token_using_the_rules_for(self.insertion_mode, token);
+    }
+
+    fn process_token_using_the_rules_for(&mut self, insertion_mode: InsertionMode, token: HTMLToken<'a>) {
+        match insertion_mode {
+            InsertionMode::Initial => self.initial(token),
+            InsertionMode::BeforeHtml => self.before_html(token),
+            InsertionMode::BeforeHead => self.before_head(token),
+            InsertionMode::InHead => self.in_head(token),
+            InsertionMode::InHeadNoscript => self.in_head_noscript(token),
+            InsertionMode::AfterHead => self.after_head(token),
+            InsertionMode::InBody => self.in_body(token),
+            InsertionMode::Text => self.text(token),
+            InsertionMode::InTable => self.in_table(token),
+            InsertionMode::InTableText => self.in_table_text(token),
+            InsertionMode::InCaption => self.in_caption(token),
+            InsertionMode::InColumnGroup => self.in_column_group(token),
+            InsertionMode::InTableBody => self.in_table_body(token),
+            InsertionMode::InRow => self.in_row(token),
+            InsertionMode::InCell => self.in_cell(token),
+            InsertionMode::InSelect => self.in_select(token),
+            InsertionMode::InSelectInTable => self.in_select_in_table(token),
+            InsertionMode::InTemplate => self.in_template(token),
+            InsertionMode::AfterBody => self.after_body(token),
+            InsertionMode::InFrameset => self.in_frameset(token),
+            InsertionMode::AfterFrameset => self.after_frameset(token),
+            InsertionMode::AfterAfterBody => self.after_after_body(token),
+            InsertionMode::AfterAfterFrameset => self.after_after_frameset(token),
+        }
+    }
+
+    fn switch_insertion_mode(&mut self, insertion_mode: InsertionMode) {
+        self.insertion_mode = insertion_mode;
+    }
+
+    // The "initial" insertion mode
+    fn initial(&mut self, token: HTMLToken<'a>) {
+        match token {
+            HTMLToken::Character(Character { ref data, .. }) if is_whitespace(data) => {
+                // ignore the token
+            }
+            HTMLToken::Comment(Comment { data, .. }) => {
+                let document = self.document.document();
+                self.insert_comment(data, Some(document));
+            }
+            HTMLToken::Doctype(doctype) => {
+                // a parse error if the doctype isn't `<!DOCTYPE html>` or one of the few legacy
+                // doctypes still allowed, but the doctype is inserted either way
+                self.document.set_quirks_mode(quirks_mode(&doctype));
+                let doctype_node = self
+                    .document
+                    .create_node(NodeKind::DocumentType(doctype.into()));
+                let document = self.document.document();
+                self.document.append_child(document, doctype_node);
+                self.switch_insertion_mode(InsertionMode::BeforeHtml);
+            }
+            _ => {
+                // parse error. a document without a doctype is rendered in quirks mode
+                self.document.set_quirks_mode(QuirksMode::Quirks);
+                self.switch_insertion_mode(InsertionMode::BeforeHtml);
+                self.reprocess_token(token);
+            }
+        }
+    }
+
+    // The "before html" insertion mode
+    fn before_html(&mut self, token: HTMLToken<'a>) {
+        match token {
+            HTMLToken::Doctype(_) => {
+                // parse error. ignore the token
+            }
+            HTMLToken::Comment(Comment { data, .. }) => {
+                let document = self.document.document();
+                self.insert_comment(data, Some(document));
+            }
+            HTMLToken::Character(Character { ref data, .. }) if is_whitespace(data) => {
+                // ignore the token
+            }
+            HTMLToken::Tag(Tag::StartTag {
+                ref tag_name,
+                ref attributes,
+                ..
+            }) if tag_name == "html" => {
+                let html_element = self.create_element(tag_name, attributes.clone());
+                let document = self.document.document();
+                self.document.append_child(document, html_element);
+                self.stack_of_open_elements.push(html_element);
+                self.switch_insertion_mode(InsertionMode::BeforeHead);
+            }
+            HTMLToken::Tag(Tag::EndTag { ref tag_name, .. })
+                if !matches!(tag_name.as_ref(), "head" | "body" | "html" | "br") =>
+            {
+                // parse error. ignore the token
+            }
+            _ => {
+                let html_element = self.create_element("html", Vec::new());
+                let document = self.document.document();
+                self.document.append_child(document, html_element);
+                self.stack_of_open_elements.push(html_element);
+                self.switch_insertion_mode(InsertionMode::BeforeHead);
+                self.reprocess_token(token);
+            }
+        }
+    }
+
+    // The "before head" insertion mode
+    fn before_head(&mut self, token: HTMLToken<'a>) {
+        match token {
+            HTMLToken::Character(Character { ref data, .. }) if is_whitespace(data) => {
+                // ignore the token
+            }
+            HTMLToken::Comment(Comment { data, .. }) => self.insert_comment(data, None),
+            HTMLToken::Doctype(_) => {
+                // parse error. ignore the token
+            }
+            HTMLToken::Tag(Tag::StartTag { ref tag_name, .. }) if tag_name == "html" => {
+                self.in_body(token);
+            }
+            HTMLToken::Tag(Tag::StartTag {
+                ref tag_name,
+                ref attributes,
+                ..
+            }) if tag_name == "head" => {
+                let head_element = self.insert_html_element(tag_name, attributes.clone());
+                self.head_element_pointer = Some(head_element);
+                self.switch_insertion_mode(InsertionMode::InHead);
+            }
+            HTMLToken::Tag(Tag::EndTag { ref tag_name, .. })
+                if !matches!(tag_name.as_ref(), "head" | "body" | "html" | "br") =>
+            {
+                // parse error. ignore the token
+            }
+            _ => {
+                let head_element = self.insert_html_element("head", Vec::new());
+                self.head_element_pointer = Some(head_element);
+                self.switch_insertion_mode(InsertionMode::InHead);
+                self.reprocess_token(token);
+            }
+        }
+    }
+
+    // The "in head" insertion mode
+    fn in_head(&mut self, token: HTMLToken<'a>) {
+        match token {
+            HTMLToken::Character(Character { ref data, .. }) if is_whitespace(data) => {
+                self.insert_characters(data);
+            }
+            HTMLToken::Comment(Comment { data, .. }) => self.insert_comment(data, None),
+            HTMLToken::Doctype(_) => {
+                // parse error. ignore the token
+            }
+            HTMLToken::Tag(Tag::StartTag {
+                ref tag_name,
+                ref attributes,
+                ..
+            }) => match tag_name.as_ref() {
+                "html" => self.in_body(token),
+                "base" | "basefont" | "bgsound" | "link" | "meta" => {
+                    // these are void elements, so they're popped straight away
+                    self.insert_html_element(tag_name, attributes.clone());
+                    self.stack_of_open_elements.pop();
+                }
+                "title" => {
+                    self.parse_generic_text_element(tag_name, attributes.clone(), HTMLTokenizerState::RCDATA);
+                }
+                "noscript" => {
+                    // scripting is disabled, so the contents of noscript are parsed as markup
+                    self.insert_html_element(tag_name, attributes.clone());
+                    self.switch_insertion_mode(InsertionMode::InHeadNoscript);
+                }
+                "noframes" | "style" => {
+                    self.parse_generic_text_element(tag_name, attributes.clone(), HTMLTokenizerState::RAWTEXT);
+                }
+                "script" => {
+                    self.parse_generic_text_element(tag_name, attributes.clone(), HTMLTokenizerState::ScriptData);
+                }
+                "template" => {
+                    self.insert_html_element(tag_name, attributes.clone());
+                    self.list_of_active_formatting_elements
+                        .push(ActiveFormattingElement::Marker);
+                    self.frameset_ok = false;
+                    self.switch_insertion_mode(InsertionMode::InTemplate);
+                    self.stack_of_template_insertion_modes
+                        .push(InsertionMode::InTemplate);
+                }
+                "head" => {
+                    // parse error. ignore the token
+                }
+                _ => self.anything_else_in_head(token),
+            },
+            HTMLToken::Tag(Tag::EndTag { ref tag_name, .. }) => match tag_name.as_ref() {
+                "head" => {
+                    self.stack_of_open_elements.pop();
+                    self.switch_insertion_mode(InsertionMode::AfterHead);
+                }
+                "body" | "html" | "br" => self.anything_else_in_head(token),
+                "template" => {
+                    if !self.stack_has_element("template") {
+                        // parse error. ignore the token
+                        return;
+                    }
+                    // if the current node isn't a template element now, this is a parse error
+                    self.generate_all_implied_end_tags_thoroughly();
+                    self.close_the_template();
+                }
+                _ => {
+                    // parse error. ignore the token
+                }
+            },
+            _ => self.anything_else_in_head(token),
+        }
+    }
+
+    fn anything_else_in_head(&mut self, token: HTMLToken<'a>) {
+        // pop the head element off the stack of open elements
+        self.stack_of_open_elements.pop();
+        self.switch_insertion_mode(InsertionMode::AfterHead);
+        self.reprocess_token(token);
+    }
+
+    // The "in head noscript" insertion mode
+    fn in_head_noscript(&mut self, token: HTMLToken<'a>) {
+        match token {
+            HTMLToken::Doctype(_) => {
+                // parse error. ignore the token
+            }
+            HTMLToken::Tag(Tag::StartTag { ref tag_name, .. }) if tag_name == "html" => {
+                self.in_body(token);
+            }
+            HTMLToken::Tag(Tag::EndTag { ref tag_name, .. }) if tag_name == "noscript" => {
+                self.stack_of_open_elements.pop();
+                self.switch_insertion_mode(InsertionMode::InHead);
+            }
+            HTMLToken::Character(Character { ref data, .. }) if is_whitespace(data) => {
+                self.in_head(token);
+            }
+            HTMLToken::Comment(_) => self.in_head(token),
+            HTMLToken::Tag(Tag::StartTag { ref tag_name, .. })
+                if matches!(
+                    tag_name.as_ref(),
+                    "basefont" | "bgsound" | "link" | "meta" | "noframes" | "style"
+                ) =>
+            {
+                self.in_head(token);
+            }
+            HTMLToken::Tag(Tag::StartTag { ref tag_name, .. })
+                if matches!(tag_name.as_ref(), "head" | "noscript") =>
+            {
+                // parse error. ignore the token
+            }
+            HTMLToken::Tag(Tag::EndTag { ref tag_name, .. }) if tag_name != "br" => {
+                // parse error. ignore the token
+            }
+            _ => {
+                // parse error. pop the noscript element and go back to the head
+                self.stack_of_open_elements.pop();
+                self.switch_insertion_mode(InsertionMode::InHead);
+                self.reprocess_token(token);
+            }
+        }
+    }
+
+    // The "after head" insertion mode
+    fn after_head(&mut self, token: HTMLToken<'a>) {
+        match token {
+            HTMLToken::Character(Character { ref data, .. }) if is_whitespace(data) => {
+                self.insert_characters(data);
+            }
+            HTMLToken::Comment(Comment { data, .. }) => self.insert_comment(data, None),
+            HTMLToken::Doctype(_) => {
+                // parse error. ignore the token
+            }
+            HTMLToken::Tag(Tag::StartTag {
+                ref tag_name,
+                ref attributes,
+                ..
+            }) => match tag_name.as_ref() {
+                "html" => self.in_body(token),
+                "body" => {
+                    self.insert_html_element(tag_name, attributes.clone());
+                    self.frameset_ok = false;
+                    self.switch_insertion_mode(InsertionMode::InBody);
+                }
+                "frameset" => {
+                    self.insert_html_element(tag_name, attributes.clone());
+                    self.switch_insertion_mode(InsertionMode::InFrameset);
+                }
+                "base" | "basefont" | "bgsound" | "link" | "meta" | "noframes" | "script"
+                | "style" | "template" | "title" => {
+                    // parse error. these belong in the head, so the head element is put back on
+                    // the stack while the token is processed
+                    if let Some(head_element) = self.head_element_pointer {
+                        self.stack_of_open_elements.push(head_element);
+                        self.in_head(token);
+                        self.stack_of_open_elements.retain(|node| *node != head_element);
+                    }
+                }
+                "head" => {
+                    // parse error. ignore the token
+                }
+                _ => self.anything_else_after_head(token),
+            },
+            HTMLToken::Tag(Tag::EndTag { ref tag_name, .. }) => match tag_name.as_ref() {
+                "template" => self.in_head(token),
+                "body" | "html" | "br" => self.anything_else_after_head(token),
+                _ => {
+                    // parse error. ignore the token
+                }
+            },
+            _ => self.anything_else_after_head(token),
+        }
+    }
+
+    fn anything_else_after_head(&mut self, token: HTMLToken<'a>) {
+        self.insert_html_element("body", Vec::new());
+        self.switch_insertion_mode(InsertionMode::InBody);
+        self.reprocess_token(token);
+    }
+
+    // The "in body" insertion mode
+    fn in_body(&mut self, token: HTMLToken<'a>) {
+        match token {
+            HTMLToken::Character(Character { data, .. }) => {
+                // null characters are a parse error, they're dropped
+                let data = data.replace('\0', "");
+                if data.is_empty() {
+                    return;
+                }
+                self.reconstruct_the_active_formatting_elements();
+                self.insert_characters(&data);
+                if !is_whitespace(&data) {
+                    self.frameset_ok = false;
+                }
+            }
+            HTMLToken::Comment(Comment { data, .. }) => self.insert_comment(data, None),
+            HTMLToken::Doctype(_) => {
+                // parse error. ignore the token
+            }
+            HTMLToken::Tag(Tag::StartTag {
+                ref tag_name,
+                ref attributes,
+                ..
+            }) => match tag_name.as_ref() {
+                "html" => {
+                    // parse error. add any attribute the html element doesn't have yet, unless
+                    // this is inside a template
+                    if self.stack_has_element("template") {
+                        return;
+                    }
+                    let html_element = self.stack_of_open_elements[0];
+                    self.add_missing_attributes(html_element, attributes);
+                }
+                "base" | "basefont" | "bgsound" | "link" | "meta" | "noframes" | "script"
+                | "style" | "template" | "title" => self.in_head(token),
+                "body" => {
+                    // parse error
+                    let body_element = self.stack_of_open_elements.get(1).copied();
+                    if let Some(body_element) = body_element {
+                        if self.node_name(body_element) == "body"
+                            && !self.stack_has_element("template")
+                        {
+                            self.frameset_ok = false;
+                            self.add_missing_attributes(body_element, attributes);
+                        }
+                    }
+                }
+                "frameset" => {
+                    // parse error
+                    let body_element = self.stack_of_open_elements.get(1).copied();
+                    if let Some(body_element) = body_element {
+                        if self.node_name(body_element) == "body" && self.frameset_ok {
+                            self.document.remove_from_parent(body_element);
+                            // pop everything but the html element
+                            self.stack_of_open_elements.truncate(1);
+                            self.insert_html_element(tag_name, attributes.clone());
+                            self.switch_insertion_mode(InsertionMode::InFrameset);
+                        }
+                    }
+                }
+                "address" | "article" | "aside" | "blockquote" | "center" | "details" | "dialog"
+                | "dir" | "div" | "dl" | "fieldset" | "figcaption" | "figure" | "footer"
+                | "header" | "hgroup" | "main" | "menu" | "nav" | "ol" | "p" | "search"
+                | "section" | "summary" | "ul" => {
+                    self.close_a_p_element_in_button_scope();
+                    self.insert_html_element(tag_name, attributes.clone());
+                }
+                "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                    self.close_a_p_element_in_button_scope();
+                    if is_heading(self.node_name(self.current_node())) {
+                        // parse error. headings can't be nested
+                        self.stack_of_open_elements.pop();
+                    }
+                    self.insert_html_element(tag_name, attributes.clone());
+                }
+                "table" => {
+                    // in quirks mode a table can go inside a paragraph, like it did in old browsers
+                    if self.document.quirks_mode() != QuirksMode::Quirks {
+                        self.close_a_p_element_in_button_scope();
+                    }
+                    self.insert_html_element(tag_name, attributes.clone());
+                    self.frameset_ok = false;
+                    self.switch_insertion_mode(InsertionMode::InTable);
+                }
+                "pre" | "listing" => {
+                    self.close_a_p_element_in_button_scope();
+                    self.insert_html_element(tag_name, attributes.clone());
+                    // like in a textarea, a newline right after the start tag is dropped
+                    self.ignore_next_line_feed = true;
+                    self.frameset_ok = false;
+                }
+                "form" => {
+                    let has_template = self.stack_has_element("template");
+                    if self.form_element_pointer.is_some() && !has_template {
+                        // parse error. forms can't be nested, ignore the token
+                        return;
+                    }
+                    self.close_a_p_element_in_button_scope();
+                    let form_element = self.insert_html_element(tag_name, attributes.clone());
+                    if !has_template {
+                        self.form_element_pointer = Some(form_element);
+                    }
+                }
+                "li" => {
+                    self.frameset_ok = false;
+                    self.close_list_item(&["li"]);
+                    self.close_a_p_element_in_button_scope();
+                    self.insert_html_element(tag_name, attributes.clone());
+                }
+                "dd" | "dt" => {
+                    self.frameset_ok = false;
+                    self.close_list_item(&["dd", "dt"]);
+                    self.close_a_p_element_in_button_scope();
+                    self.insert_html_element(tag_name, attributes.clone());
+                }
+                "button" => {
+                    if self.has_an_element_in_scope("button") {
+                        // parse error
+                        self.generate_implied_end_tags(None);
+                        self.pop_until_element_has_been_popped("button");
+                    }
+                    self.reconstruct_the_active_formatting_elements();
+                    self.insert_html_element(tag_name, attributes.clone());
+                    self.frameset_ok = false;
+                }
+                "select" => {
+                    self.reconstruct_the_active_formatting_elements();
+                    self.insert_html_element(tag_name, attributes.clone());
+                    self.frameset_ok = false;
+                    // a select in a table gets closed by the table parts that come after it
+                    let in_table = matches!(
+                        self.insertion_mode,
+                        InsertionMode::InTable
+                            | InsertionMode::InCaption
+                            | InsertionMode::InTableBody
+                            | InsertionMode::InRow
+                            | InsertionMode::InCell
+                    );
+                    if in_table {
+                        self.switch_insertion_mode(InsertionMode::InSelectInTable);
+                    } else {
+                        self.switch_insertion_mode(InsertionMode::InSelect);
+                    }
+                }
+                "optgroup" | "option" => {
+                    if self.node_name(self.current_node()) == "option" {
+                        self.stack_of_open_elements.pop();
+                    }
+                    self.reconstruct_the_active_formatting_elements();
+                    self.insert_html_element(tag_name, attributes.clone());
+                }
+                "rb" | "rtc" => {
+                    if self.has_an_element_in_scope("ruby") {
+                        // if the current node isn't a ruby element now, this is a parse error
+                        self.generate_implied_end_tags(None);
+                    }
+                    self.insert_html_element(tag_name, attributes.clone());
+                }
+                "rp" | "rt" => {
+                    if self.has_an_element_in_scope("ruby") {
+                        // if the current node isn't a ruby or rtc element now, this is a parse
+                        // error
+                        self.generate_implied_end_tags(Some("rtc"));
+                    }
+                    self.insert_html_element(tag_name, attributes.clone());
+                }
+                "math" | "svg" => {
+                    self.reconstruct_the_active_formatting_elements();
+                    let mut attributes = attributes.clone();
+                    let namespace = if tag_name == "math" {
+                        adjust_mathml_attributes(&mut attributes);
+                        Namespace::MathML
+                    } else {
+                        adjust_svg_attributes(&mut attributes);
+                        Namespace::Svg
+                    };
+                    adjust_foreign_attributes(&mut attributes);
+                    self.insert_foreign_element(tag_name, namespace, attributes);
+                    if let HTMLToken::Tag(Tag::StartTag { self_closing: true, .. }) = token {
+                        self.stack_of_open_elements.pop();
+                    }
+                }
+                "caption" | "col" | "colgroup" | "frame" | "tbody" | "td" | "tfoot" | "th"
+                | "thead" | "tr" => {
+                    // parse error. ignore the token
+                }
+                "area" | "br" | "embed" | "img" | "keygen" | "wbr" => {
+                    self.reconstruct_the_active_formatting_elements();
+                    self.insert_html_element(tag_name, attributes.clone());
+                    self.stack_of_open_elements.pop();
+                    self.frameset_ok = false;
+                }
+                "input" => {
+                    self.reconstruct_the_active_formatting_elements();
+                    self.insert_html_element(tag_name, attributes.clone());
+                    self.stack_of_open_elements.pop();
+                    let is_hidden = attributes.iter().any(|attribute| {
+                        attribute.key == "type" && attribute.value.eq_ignore_ascii_case("hidden")
+                    });
+                    if !is_hidden {
+                        self.frameset_ok = false;
+                    }
+                }
+                "param" | "source" | "track" => {
+                    self.insert_html_element(tag_name, attributes.clone());
+                    self.stack_of_open_elements.pop();
+                }
+                "hr" => {
+                    self.close_a_p_element_in_button_scope();
+                    self.insert_html_element(tag_name, attributes.clone());
+                    self.stack_of_open_elements.pop();
+                    self.frameset_ok = false;
+                }
+                "image" => {
+                    // parse error. it's treated as an img start tag, don't ask
+                    self.reprocess_token(HTMLToken::Tag(Tag::StartTag {
+                        tag_name: Cow::Borrowed("img"),
+                        self_closing: false,
+                        attributes: attributes.clone(),
+                        original_tag_name: None,
+                        span: token.span().unwrap_or_default(),
+                    }));
+                }
+                "textarea" => {
+                    self.insert_html_element(tag_name, attributes.clone());
+                    self.ignore_next_line_feed = true;
+                    self.tokenizer.switch_state(HTMLTokenizerState::RCDATA);
+                    self.original_insertion_mode = Some(self.insertion_mode);
+                    self.frameset_ok = false;
+                    self.switch_insertion_mode(InsertionMode::Text);
+                }
+                "xmp" => {
+                    self.close_a_p_element_in_button_scope();
+                    self.reconstruct_the_active_formatting_elements();
+                    self.frameset_ok = false;
+                    self.parse_generic_text_element(tag_name, attributes.clone(), HTMLTokenizerState::RAWTEXT);
+                }
+                "iframe" => {
+                    self.frameset_ok = false;
+                    self.parse_generic_text_element(tag_name, attributes.clone(), HTMLTokenizerState::RAWTEXT);
+                }
+                "noembed" => {
+                    self.parse_generic_text_element(tag_name, attributes.clone(), HTMLTokenizerState::RAWTEXT);
+                }
+                "plaintext" => {
+                    // everything after the start tag is text, there's no way out of it
+                    self.close_a_p_element_in_button_scope();
+                    self.insert_html_element(tag_name, attributes.clone());
+                    self.tokenizer.switch_state(HTMLTokenizerState::PLAINTEXT);
+                }
+                "a" => {
+                    // an <a> can't be nested in another one, the one that's still open is closed
+                    // first (parse error)
+                    if let Some(a_element) = self.active_formatting_element_after_last_marker("a") {
+                        self.run_the_adoption_agency_algorithm("a");
+                        self.remove_from_the_list_of_active_formatting_elements(a_element);
+                        self.stack_of_open_elements.retain(|node| *node != a_element);
+                    }
+                    self.reconstruct_the_active_formatting_elements();
+                    let element = self.insert_html_element(tag_name, attributes.clone());
+                    self.push_onto_the_list_of_active_formatting_elements(element);
+                }
+                "b" | "big" | "code" | "em" | "font" | "i" | "s" | "small" | "strike" | "strong"
+                | "tt" | "u" => {
+                    self.reconstruct_the_active_formatting_elements();
+                    let element = self.insert_html_element(tag_name, attributes.clone());
+                    self.push_onto_the_list_of_active_formatting_elements(element);
+                }
+                "nobr" => {
+                    self.reconstruct_the_active_formatting_elements();
+                    if self.has_an_element_in_scope("nobr") {
+                        // parse error
+                        self.run_the_adoption_agency_algorithm("nobr");
+                        self.reconstruct_the_active_formatting_elements();
+                    }
+                    let element = self.insert_html_element(tag_name, attributes.clone());
+                    self.push_onto_the_list_of_active_formatting_elements(element);
+                }
+                "applet" | "marquee" | "object" => {
+                    self.reconstruct_the_active_formatting_elements();
+                    self.insert_html_element(tag_name, attributes.clone());
+                    self.list_of_active_formatting_elements
+                        .push(ActiveFormattingElement::Marker);
+                    self.frameset_ok = false;
+                }
+                "head" => {
+                    // parse error. ignore the token
+                }
+                _ => {
+                    self.reconstruct_the_active_formatting_elements();
+                    self.insert_html_element(tag_name, attributes.clone());
+                }
+            },
+            HTMLToken::Tag(Tag::EndTag { ref tag_name, .. }) => match tag_name.as_ref() {
+                "body" => {
+                    if self.has_an_element_in_scope("body") {
+                        self.switch_insertion_mode(InsertionMode::AfterBody);
+                    }
+                }
+                "html" => {
+                    if self.has_an_element_in_scope("body") {
+                        self.switch_insertion_mode(InsertionMode::AfterBody);
+                        self.reprocess_token(token);
+                    }
+                }
+                "address" | "article" | "aside" | "blockquote" | "button" | "center" | "details"
+                | "dialog" | "dir" | "div" | "dl" | "fieldset" | "figcaption" | "figure"
+                | "footer" | "header" | "hgroup" | "listing" | "main" | "menu" | "nav" | "ol"
+                | "pre" | "search" | "section" | "summary" | "ul" => {
+                    if self.has_an_element_in_scope(tag_name) {
+                        // if the current node isn't an element with the same tag name now, this
+                        // is a parse error
+                        self.generate_implied_end_tags(None);
+                        self.pop_until_element_has_been_popped(tag_name);
+                    }
+                }
+                "form" => {
+                    if self.stack_has_element("template") {
+                        if self.has_an_element_in_scope("form") {
+                            self.generate_implied_end_tags(None);
+                            self.pop_until_element_has_been_popped("form");
+                        }
+                        return;
+                    }
+                    let form_element = self.form_element_pointer.take();
+                    if let Some(form_element) = form_element {
+                        if self.has_a_particular_element_in_scope(form_element) {
+                            // the form element is taken off the stack even if it isn't the
+                            // current node, whatever was opened inside it stays open
+                            self.generate_implied_end_tags(None);
+                            self.stack_of_open_elements.retain(|node| *node != form_element);
+                        }
+                    }
+                }
+                "p" => {
+                    if !self.has_an_element_in_specific_scope("p", BUTTON_SCOPE) {
+                        // parse error. a </p> without a <p> gets an empty paragraph
+                        self.insert_html_element("p", Vec::new());
+                    }
+                    self.close_a_p_element();
+                }
+                "li" => {
+                    if self.has_an_element_in_specific_scope("li", LIST_ITEM_SCOPE) {
+                        self.generate_implied_end_tags(Some("li"));
+                        self.pop_until_element_has_been_popped("li");
+                    }
+                }
+                "dd" | "dt" => {
+                    if self.has_an_element_in_scope(tag_name) {
+                        self.generate_implied_end_tags(Some(tag_name));
+                        self.pop_until_element_has_been_popped(tag_name);
+                    }
+                }
+                "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                    // any heading closes any other heading, so </h2> closes an <h1>
+                    let has_heading_in_scope = ["h1", "h2", "h3", "h4", "h5", "h6"]
+                        .iter()
+                        .any(|heading| self.has_an_element_in_scope(heading));
+                    if has_heading_in_scope {
+                        self.generate_implied_end_tags(None);
+                        while let Some(node) = self.stack_of_open_elements.pop() {
+                            if is_heading(self.node_name(node)) {
+                                break;
+                            }
+                        }
+                    }
+                }
+                "applet" | "marquee" | "object" => {
+                    if self.has_an_element_in_scope(tag_name) {
+                        self.generate_implied_end_tags(None);
+                        self.pop_until_element_has_been_popped(tag_name);
+                        self.clear_the_list_of_active_formatting_elements_up_to_the_last_marker();
+                    }
+                }
+                "a" | "b" | "big" | "code" | "em" | "font" | "i" | "nobr" | "s" | "small"
+                | "strike" | "strong" | "tt" | "u" => {
+                    if !self.run_the_adoption_agency_algorithm(tag_name) {
+                        self.any_other_end_tag_in_body(tag_name);
+                    }
+                }
+                "br" => {
+                    // parse error. treated as a <br> start tag
+                    self.reprocess_token(HTMLToken::Tag(Tag::StartTag {
+                        tag_name: Cow::Borrowed("br"),
+                        self_closing: false,
+                        attributes: Vec::new(),
+                        original_tag_name: None,
+                        span: token.span().unwrap_or_default(),
+                    }));
+                }
+                "template" => self.in_head(token),
+                _ => self.any_other_end_tag_in_body(tag_name),
+            },
+            HTMLToken::EndOfFile => {
+                if self.stack_of_template_insertion_modes.is_empty() {
+                    self.stop_parsing();
+                } else {
+                    self.in_template(token);
+                }
+            }
+        }
+    }
+
+    fn any_other_end_tag_in_body(&mut self, tag_name: &str) {
+        for index in (0..self.stack_of_open_elements.len()).rev() {
+            let node = self.stack_of_open_elements[index];
+            if self.node_name(node) == tag_name {
+                // if node isn't the current node, this is a parse error
+                let tag_name = tag_name.to_string();
+                self.generate_implied_end_tags(Some(&tag_name));
+                self.stack_of_open_elements.truncate(index);
+                return;
+            } else if self.is_special_element(node) {
+                // parse error. ignore the token
+                return;
+            }
+        }
+    }
+
+    /// Fixes up misnested formatting elements like `<b><i></b></i>` when the end tag of a
+    /// formatting element is seen, the way browsers do, see
+    /// https://html.spec.whatwg.org/multipage/parsing.html#adoption-agency-algorithm
+    ///
+    /// Returns false if there's no such formatting element in the list of active formatting
+    /// elements, in which case the end tag should be handled like any other end tag
+    fn run_the_adoption_agency_algorithm(&mut self, subject: &str) -> bool {
+        let current_node = self.current_node();
+        if self.node_name(current_node) == subject
+            && !self.is_in_the_list_of_active_formatting_elements(current_node)
+        {
+            self.stack_of_open_elements.pop();
+            return true;
+        }
+
+        for _ in 0..8 {
+            let Some(formatting_element) = self.active_formatting_element_after_last_marker(subject)
+            else {
+                return false;
+            };
+            let Some(formatting_element_index) = self.position_in_stack(formatting_element) else {
+                // parse error
+                self.remove_from_the_list_of_active_formatting_elements(formatting_element);
+                return true;
+            };
+            if !self.has_a_particular_element_in_scope(formatting_element) {
+                // parse error. ignore the token
+                return true;
+            }
+            // if the formatting element isn't the current node, this is a parse error
+
+            // the furthest block is the topmost special element below the formatting element
+            let furthest_block_index = (formatting_element_index + 1
+                ..self.stack_of_open_elements.len())
+                .find(|index| self.is_special_element(self.stack_of_open_elements[*index]));
+            let Some(furthest_block_index) = furthest_block_index else {
+                self.stack_of_open_elements.truncate(formatting_element_index);
+                self.remove_from_the_list_of_active_formatting_elements(formatting_element);
+                return true;
+            };
+            let furthest_block = self.stack_of_open_elements[furthest_block_index];
+            let common_ancestor = self.stack_of_open_elements[formatting_element_index - 1];
+
+            // where the formatting element's replacement goes in the list of active formatting
+            // elements, kept up to date as entries before it are removed
+            let mut bookmark = self
+                .position_in_the_list_of_active_formatting_elements(formatting_element)
+                .expect("the formatting element should be in the list of active formatting elements");
+
+            let mut node_index = furthest_block_index;
+            let mut last_node = furthest_block;
+            let mut inner_loop_counter = 0;
+            loop {
+                inner_loop_counter += 1;
+                // nodes taken off the stack below don't change the index of the nodes above them
+                node_index -= 1;
+                let node = self.stack_of_open_elements[node_index];
+                if node == formatting_element {
+                    break;
+                }
+
+                let mut list_index = self.position_in_the_list_of_active_formatting_elements(node);
+                if inner_loop_counter > 3 {
+                    if let Some(index) = list_index.take() {
+                        self.list_of_active_formatting_elements.remove(index);
+                        if index < bookmark {
+                            bookmark -= 1;
+                        }
+                    }
+                }
+                let Some(list_index) = list_index else {
+                    self.stack_of_open_elements.remove(node_index);
+                    continue;
+                };
+
+                let new_element = self.clone_element(node);
+                self.list_of_active_formatting_elements[list_index] =
+                    ActiveFormattingElement::Element(new_element);
+                self.stack_of_open_elements[node_index] = new_element;
+                if last_node == furthest_block {
+                    bookmark = list_index + 1;
+                }
+                self.document.append_child(new_element, last_node);
+                last_node = new_element;
+            }
+
+            let location = self.appropriate_place_for_inserting_a_node(Some(common_ancestor));
+            self.document
+                .insert_before(location.parent, last_node, location.before);
+
+            // the formatting element is recreated inside the furthest block, and takes over
+            // everything that was in it
+            let new_element = self.clone_element(formatting_element);
+            while let Some(child) = self.document.first_child(furthest_block) {
+                self.document.append_child(new_element, child);
+            }
+            self.document.append_child(furthest_block, new_element);
+
+            if let Some(index) = self.position_in_the_list_of_active_formatting_elements(formatting_element) {
+                self.list_of_active_formatting_elements.remove(index);
+                if index < bookmark {
+                    bookmark -= 1;
+                }
+            }
+            self.list_of_active_formatting_elements
+                .insert(bookmark, ActiveFormattingElement::Element(new_element));
+
+            self.stack_of_open_elements.retain(|node| *node != formatting_element);
+            let furthest_block_index = self
+                .position_in_stack(furthest_block)
+                .expect("the furthest block should still be on the stack of open elements");
+            self.stack_of_open_elements
+                .insert(furthest_block_index + 1, new_element);
+        }
+        true
+    }
+
+    // The "in table" insertion mode
+    fn in_table(&mut self, token: HTMLToken<'a>) {
+        match token {
+            HTMLToken::Character(_)
+                if matches!(
+                    self.node_name(self.current_node()),
+                    "table" | "tbody" | "template" | "tfoot" | "thead" | "tr"
+                ) =>
+            {
+                self.pending_table_character_tokens.clear();
+                self.original_insertion_mode = Some(self.insertion_mode);
+                self.switch_insertion_mode(InsertionMode::InTableText);
+                self.reprocess_token(token);
+            }
+            HTMLToken::Comment(Comment { data, .. }) => self.insert_comment(data, None),
+            HTMLToken::Doctype(_) => {
+                // parse error. ignore the token
+            }
+            HTMLToken::Tag(Tag::StartTag {
+                ref tag_name,
+                ref attributes,
+                ..
+            }) => match tag_name.as_ref() {
+                "caption" => {
+                    self.clear_the_stack_back_to(TABLE_CONTEXT);
+                    self.list_of_active_formatting_elements
+                        .push(ActiveFormattingElement::Marker);
+                    self.insert_html_element(tag_name, attributes.clone());
+                    self.switch_insertion_mode(InsertionMode::InCaption);
+                }
+                "colgroup" => {
+                    self.clear_the_stack_back_to(TABLE_CONTEXT);
+                    self.insert_html_element(tag_name, attributes.clone());
+                    self.switch_insertion_mode(InsertionMode::InColumnGroup);
+                }
+                "col" => {
+                    self.clear_the_stack_back_to(TABLE_CONTEXT);
+                    self.insert_html_element("colgroup", Vec::new());
+                    self.switch_insertion_mode(InsertionMode::InColumnGroup);
+                    self.reprocess_token(token);
+                }
+                "tbody" | "tfoot" | "thead" => {
+                    self.clear_the_stack_back_to(TABLE_CONTEXT);
+                    self.insert_html_element(tag_name, attributes.clone());
+                    self.switch_insertion_mode(InsertionMode::InTableBody);
+                }
+                "td" | "th" | "tr" => {
+                    self.clear_the_stack_back_to(TABLE_CONTEXT);
+                    self.insert_html_element("tbody", Vec::new());
+                    self.switch_insertion_mode(InsertionMode::InTableBody);
+                    self.reprocess_token(token);
+                }
+                "table" => {
+                    // parse error. the open table is closed and the new one starts after it
+                    if self.has_an_element_in_specific_scope("table", TABLE_SCOPE) {
+                        self.pop_until_element_has_been_popped("table");
+                        self.reset_the_insertion_mode_appropriately();
+                        self.reprocess_token(token);
+                    }
+                }
+                "style" | "script" | "template" => self.in_head(token),
+                "input"
+                    if attributes.iter().any(|attribute| {
+                        attribute.key == "type" && attribute.value.eq_ignore_ascii_case("hidden")
+                    }) =>
+                {
+                    // parse error. hidden inputs can stay in the table, they aren't displayed
+                    self.insert_html_element(tag_name, attributes.clone());
+                    self.stack_of_open_elements.pop();
+                }
+                "form" => {
+                    // parse error
+                    if self.stack_has_element("template") || self.form_element_pointer.is_some() {
+                        return;
+                    }
+                    let form_element = self.insert_html_element(tag_name, attributes.clone());
+                    self.form_element_pointer = Some(form_element);
+                    self.stack_of_open_elements.pop();
+                }
+                _ => self.anything_else_in_table(token),
+            },
+            HTMLToken::Tag(Tag::EndTag { ref tag_name, .. }) => match tag_name.as_ref() {
+                "table" => {
+                    if self.has_an_element_in_specific_scope("table", TABLE_SCOPE) {
+                        self.pop_until_element_has_been_popped("table");
+                        self.reset_the_insertion_mode_appropriately();
+                    }
+                }
+                "body" | "caption" | "col" | "colgroup" | "html" | "tbody" | "td" | "tfoot"
+                | "th" | "thead" | "tr" => {
+                    // parse error. ignore the token
+                }
+                "template" => self.in_head(token),
+                _ => self.anything_else_in_table(token),
+            },
+            HTMLToken::EndOfFile => self.in_body(token),
+            _ => self.anything_else_in_table(token),
+        }
+    }
+
+    /// Anything that doesn't belong in a table is a parse error, and ends up right before the
+    /// table instead of inside it (this is called foster parenting)
+    fn anything_else_in_table(&mut self, token: HTMLToken<'a>) {
+        self.foster_parenting = true;
+        self.in_body(token);
+        self.foster_parenting = false;
+    }
+
+    // The "in table text" insertion mode
+    fn in_table_text(&mut self, token: HTMLToken<'a>) {
+        match token {
+            HTMLToken::Character(Character { data, .. }) => {
+                // null characters are a parse error, they're dropped
+                self.pending_table_character_tokens
+                    .push_str(&data.replace('\0', ""));
+            }
+            _ => {
+                let pending_characters = std::mem::take(&mut self.pending_table_character_tokens);
+                if !is_whitespace(&pending_characters) {
+                    // parse error. text that isn't whitespace is foster parented
+                    self.anything_else_in_table(HTMLToken::Character(Character {
+                        data: Cow::Owned(pending_characters),
+                        span: Default::default(),
+                    }));
+                } else if !pending_characters.is_empty() {
+                    self.insert_characters(&pending_characters);
+                }
+                self.switch_to_original_insertion_mode();
+                self.reprocess_token(token);
+            }
+        }
+    }
+
+    // The "in caption" insertion mode
+    fn in_caption(&mut self, token: HTMLToken<'a>) {
+        match token {
+            HTMLToken::Tag(Tag::EndTag { ref tag_name, .. }) if tag_name == "caption" => {
+                self.close_the_caption();
+            }
+            HTMLToken::Tag(Tag::StartTag { ref tag_name, .. })
+                if matches!(
+                    tag_name.as_ref(),
+                    "caption" | "col" | "colgroup" | "tbody" | "td" | "tfoot" | "th" | "thead" | "tr"
+                ) =>
+            {
+                if self.close_the_caption() {
+                    self.reprocess_token(token);
+                }
+            }
+            HTMLToken::Tag(Tag::EndTag { ref tag_name, .. }) if tag_name == "table" => {
+                if self.close_the_caption() {
+                    self.reprocess_token(token);
+                }
+            }
+            HTMLToken::Tag(Tag::EndTag { ref tag_name, .. })
+                if matches!(
+                    tag_name.as_ref(),
+                    "body" | "col" | "colgroup" | "html" | "tbody" | "td" | "tfoot" | "th" | "thead"
+                        | "tr"
+                ) =>
+            {
+                // parse error. ignore the token
+            }
+            _ => self.in_body(token),
+        }
+    }
+
+    /// Returns false if there's no caption to close, in which case the token is ignored
+    fn close_the_caption(&mut self) -> bool {
+        if !self.has_an_element_in_specific_scope("caption", TABLE_SCOPE) {
+            // parse error. ignore the token
+            return false;
+        }
+        // if the current node isn't a caption element now, this is a parse error
+        self.generate_implied_end_tags(None);
+        self.pop_until_element_has_been_popped("caption");
+        self.clear_the_list_of_active_formatting_elements_up_to_the_last_marker();
+        self.switch_insertion_mode(InsertionMode::InTable);
+        true
+    }
+
+    // The "in column group" insertion mode
+    fn in_column_group(&mut self, token: HTMLToken<'a>) {
+        match token {
+            HTMLToken::Character(Character { ref data, .. }) if is_whitespace(data) => {
+                self.insert_characters(data);
+            }
+            HTMLToken::Comment(Comment { data, .. }) => self.insert_comment(data, None),
+            HTMLToken::Doctype(_) => {
+                // parse error. ignore the token
+            }
+            HTMLToken::Tag(Tag::StartTag { ref tag_name, .. }) if tag_name == "html" => {
+                self.in_body(token);
+            }
+            HTMLToken::Tag(Tag::StartTag {
+                ref tag_name,
+                ref attributes,
+                ..
+            }) if tag_name == "col" => {
+                self.insert_html_element(tag_name, attributes.clone());
+                self.stack_of_open_elements.pop();
+            }
+            HTMLToken::Tag(Tag::EndTag { ref tag_name, .. }) if tag_name == "colgroup" => {
+                if self.node_name(self.current_node()) == "colgroup" {
+                    self.stack_of_open_elements.pop();
+                    self.switch_insertion_mode(InsertionMode::InTable);
+                }
+            }
+            HTMLToken::Tag(Tag::EndTag { ref tag_name, .. }) if tag_name == "col" => {
+                // parse error. ignore the token
+            }
+            HTMLToken::Tag(Tag::StartTag { ref tag_name, .. } | Tag::EndTag { ref tag_name, .. })
+                if tag_name == "template" =>
+            {
+                self.in_head(token);
+            }
+            HTMLToken::EndOfFile => self.in_body(token),
+            _ => {
+                if self.node_name(self.current_node()) == "colgroup" {
+                    self.stack_of_open_elements.pop();
+                    self.switch_insertion_mode(InsertionMode::InTable);
+                    self.reprocess_token(token);
+                }
+            }
+        }
+    }
+
+    // The "in table body" insertion mode
+    fn in_table_body(&mut self, token: HTMLToken<'a>) {
+        match token {
+            HTMLToken::Tag(Tag::StartTag {
+                ref tag_name,
+                ref attributes,
+                ..
+            }) if tag_name == "tr" => {
+                self.clear_the_stack_back_to(TABLE_BODY_CONTEXT);
+                self.insert_html_element(tag_name, attributes.clone());
+                self.switch_insertion_mode(InsertionMode::InRow);
+            }
+            HTMLToken::Tag(Tag::StartTag { ref tag_name, .. })
+                if matches!(tag_name.as_ref(), "th" | "td") =>
+            {
+                // parse error. the row the cell should be in is made up
+                self.clear_the_stack_back_to(TABLE_BODY_CONTEXT);
+                self.insert_html_element("tr", Vec::new());
+                self.switch_insertion_mode(InsertionMode::InRow);
+                self.reprocess_token(token);
+            }
+            HTMLToken::Tag(Tag::EndTag { ref tag_name, .. })
+                if matches!(tag_name.as_ref(), "tbody" | "tfoot" | "thead") =>
+            {
+                if self.has_an_element_in_specific_scope(tag_name, TABLE_SCOPE) {
+                    self.clear_the_stack_back_to(TABLE_BODY_CONTEXT);
+                    self.stack_of_open_elements.pop();
+                    self.switch_insertion_mode(InsertionMode::InTable);
+                }
+            }
+            HTMLToken::Tag(Tag::StartTag { ref tag_name, .. })
+                if matches!(
+                    tag_name.as_ref(),
+                    "caption" | "col" | "colgroup" | "tbody" | "tfoot" | "thead"
+                ) =>
+            {
+                self.close_the_table_body(token);
+            }
+            HTMLToken::Tag(Tag::EndTag { ref tag_name, .. }) if tag_name == "table" => {
+                self.close_the_table_body(token);
+            }
+            HTMLToken::Tag(Tag::EndTag { ref tag_name, .. })
+                if matches!(
+                    tag_name.as_ref(),
+                    "body" | "caption" | "col" | "colgroup" | "html" | "td" | "th" | "tr"
+                ) =>
+            {
+                // parse error. ignore the token
+            }
+            _ => self.in_table(token),
+        }
+    }
+
+    fn close_the_table_body(&mut self, token: HTMLToken<'a>) {
+        let has_table_body_in_scope = ["tbody", "thead", "tfoot"]
+            .iter()
+            .any(|tag_name| self.has_an_element_in_specific_scope(tag_name, TABLE_SCOPE));
+        if !has_table_body_in_scope {
+            // parse error. ignore the token
+            return;
+        }
+        self.clear_the_stack_back_to(TABLE_BODY_CONTEXT);
+        self.stack_of_open_elements.pop();
+        self.switch_insertion_mode(InsertionMode::InTable);
+        self.reprocess_token(token);
+    }
+
+    // The "in row" insertion mode
+    fn in_row(&mut self, token: HTMLToken<'a>) {
+        match token {
+            HTMLToken::Tag(Tag::StartTag {
+                ref tag_name,
+                ref attributes,
+                ..
+            }) if matches!(tag_name.as_ref(), "th" | "td") => {
+                self.clear_the_stack_back_to(TABLE_ROW_CONTEXT);
+                self.insert_html_element(tag_name, attributes.clone());
+                self.switch_insertion_mode(InsertionMode::InCell);
+                self.list_of_active_formatting_elements
+                    .push(ActiveFormattingElement::Marker);
+            }
+            HTMLToken::Tag(Tag::EndTag { ref tag_name, .. }) if tag_name == "tr" => {
+                self.close_the_row();
+            }
+            HTMLToken::Tag(Tag::StartTag { ref tag_name, .. })
+                if matches!(
+                    tag_name.as_ref(),
+                    "caption" | "col" | "colgroup" | "tbody" | "tfoot" | "thead" | "tr"
+                ) =>
+            {
+                if self.close_the_row() {
+                    self.reprocess_token(token);
+                }
+            }
+            HTMLToken::Tag(Tag::EndTag { ref tag_name, .. }) if tag_name == "table" => {
+                if self.close_the_row() {
+                    self.reprocess_token(token);
+                }
+            }
+            HTMLToken::Tag(Tag::EndTag { ref tag_name, .. })
+                if matches!(tag_name.as_ref(), "tbody" | "tfoot" | "thead") =>
+            {
+                if self.has_an_element_in_specific_scope(tag_name, TABLE_SCOPE) && self.close_the_row() {
+                    self.reprocess_token(token);
+                }
+            }
+            HTMLToken::Tag(Tag::EndTag { ref tag_name, .. })
+                if matches!(
+                    tag_name.as_ref(),
+                    "body" | "caption" | "col" | "colgroup" | "html" | "td" | "th"
+                ) =>
+            {
+                // parse error. ignore the token
+            }
+            _ => self.in_table(token),
+        }
+    }
+
+    /// Returns false if there's no row to close, in which case the token is ignored
+    fn close_the_row(&mut self) -> bool {
+        if !self.has_an_element_in_specific_scope("tr", TABLE_SCOPE) {
+            // parse error. ignore the token
+            return false;
+        }
+        self.clear_the_stack_back_to(TABLE_ROW_CONTEXT);
+        self.stack_of_open_elements.pop();
+        self.switch_insertion_mode(InsertionMode::InTableBody);
+        true
+    }
+
+    // The "in cell" insertion mode
+    fn in_cell(&mut self, token: HTMLToken<'a>) {
+        match token {
+            HTMLToken::Tag(Tag::EndTag { ref tag_name, .. })
+                if matches!(tag_name.as_ref(), "td" | "th") =>
+            {
+                if self.has_an_element_in_specific_scope(tag_name, TABLE_SCOPE) {
+                    // if the current node isn't an element with the same tag name now, this is a
+                    // parse error
+                    self.generate_implied_end_tags(None);
+                    self.pop_until_element_has_been_popped(tag_name);
+                    self.clear_the_list_of_active_formatting_elements_up_to_the_last_marker();
+                    self.switch_insertion_mode(InsertionMode::InRow);
+                }
+            }
+            HTMLToken::Tag(Tag::StartTag { ref tag_name, .. })
+                if matches!(
+                    tag_name.as_ref(),
+                    "caption" | "col" | "colgroup" | "tbody" | "td" | "tfoot" | "th" | "thead" | "tr"
+                ) =>
+            {
+                let has_cell_in_scope = self.has_an_element_in_specific_scope("td", TABLE_SCOPE)
+                    || self.has_an_element_in_specific_scope("th", TABLE_SCOPE);
+                if has_cell_in_scope {
+                    self.close_the_cell();
+                    self.reprocess_token(token);
+                }
+            }
+            HTMLToken::Tag(Tag::EndTag { ref tag_name, .. })
+                if matches!(tag_name.as_ref(), "body" | "caption" | "col" | "colgroup" | "html") =>
+            {
+                // parse error. ignore the token
+            }
+            HTMLToken::Tag(Tag::EndTag { ref tag_name, .. })
+                if matches!(tag_name.as_ref(), "table" | "tbody" | "tfoot" | "thead" | "tr") =>
+            {
+                if self.has_an_element_in_specific_scope(tag_name, TABLE_SCOPE) {
+                    self.close_the_cell();
+                    self.reprocess_token(token);
+                }
+            }
+            _ => self.in_body(token),
+        }
+    }
+
+    fn close_the_cell(&mut self) {
+        // if the current node isn't a td or th element now, this is a parse error
+        self.generate_implied_end_tags(None);
+        while let Some(node) = self.stack_of_open_elements.pop() {
+            if matches!(self.node_name(node), "td" | "th") {
+                break;
+            }
+        }
+        self.clear_the_list_of_active_formatting_elements_up_to_the_last_marker();
+        self.switch_insertion_mode(InsertionMode::InRow);
+    }
+
+    // The "in select" insertion mode
+    fn in_select(&mut self, token: HTMLToken<'a>) {
+        match token {
+            HTMLToken::Character(Character { data, .. }) => {
+                // null characters are a parse error, they're dropped
+                let data = data.replace('\0', "");
+                if !data.is_empty() {
+                    self.insert_characters(&data);
+                }
+            }
+            HTMLToken::Comment(Comment { data, .. }) => self.insert_comment(data, None),
+            HTMLToken::Doctype(_) => {
+                // parse error. ignore the token
+            }
+            HTMLToken::Tag(Tag::StartTag {
+                ref tag_name,
+                ref attributes,
+                ..
+            }) => match tag_name.as_ref() {
+                "html" => self.in_body(token),
+                "option" => {
+                    if self.node_name(self.current_node()) == "option" {
+                        self.stack_of_open_elements.pop();
+                    }
+                    self.insert_html_element(tag_name, attributes.clone());
+                }
+                "optgroup" | "hr" => {
+                    if self.node_name(self.current_node()) == "option" {
+                        self.stack_of_open_elements.pop();
+                    }
+                    if self.node_name(self.current_node()) == "optgroup" {
+                        self.stack_of_open_elements.pop();
+                    }
+                    self.insert_html_element(tag_name, attributes.clone());
+                    if tag_name == "hr" {
+                        self.stack_of_open_elements.pop();
+                    }
+                }
+                "select" if self.has_an_element_in_select_scope("select") => {
+                    // parse error. a select can't be nested, this closes the open one instead
+                    self.close_the_select();
+                }
+                "input" | "keygen" | "textarea"
+                    if self.has_an_element_in_select_scope("select") =>
+                {
+                    // parse error. these close the select and are processed outside it
+                    self.close_the_select();
+                    self.reprocess_token(token);
+                }
+                "script" | "template" => self.in_head(token),
+                _ => {
+                    // parse error. ignore the token
+                }
+            },
+            HTMLToken::Tag(Tag::EndTag { ref tag_name, .. }) => match tag_name.as_ref() {
+                "optgroup" => {
+                    // </optgroup> also closes an option that's still open in the optgroup
+                    let previous_node = self.stack_of_open_elements.iter().rev().nth(1);
+                    if self.node_name(self.current_node()) == "option"
+                        && previous_node.is_some_and(|node| self.node_name(*node) == "optgroup")
+                    {
+                        self.stack_of_open_elements.pop();
+                    }
+                    if self.node_name(self.current_node()) == "optgroup" {
+                        self.stack_of_open_elements.pop();
+                    }
+                    // otherwise this is a parse error. ignore the token
+                }
+                "option" if self.node_name(self.current_node()) == "option" => {
+                    self.stack_of_open_elements.pop();
+                }
+                "select" if self.has_an_element_in_select_scope("select") => {
+                    self.close_the_select();
+                }
+                "template" => self.in_head(token),
+                _ => {
+                    // parse error. ignore the token
+                }
+            },
+            HTMLToken::EndOfFile => self.in_body(token),
+        }
+    }
+
+    // The "in select in table" insertion mode
+    fn in_select_in_table(&mut self, token: HTMLToken<'a>) {
+        match token {
+            HTMLToken::Tag(Tag::StartTag { ref tag_name, .. })
+                if is_table_part_closing_a_select(tag_name) =>
+            {
+                // parse error
+                self.close_the_select();
+                self.reprocess_token(token);
+            }
+            HTMLToken::Tag(Tag::EndTag { ref tag_name, .. })
+                if is_table_part_closing_a_select(tag_name) =>
+            {
+                // parse error
+                if self.has_an_element_in_specific_scope(tag_name, TABLE_SCOPE) {
+                    self.close_the_select();
+                    self.reprocess_token(token);
+                }
+            }
+            _ => self.in_select(token),
+        }
+    }
+
+    fn close_the_select(&mut self) {
+        self.pop_until_element_has_been_popped("select");
+        self.reset_the_insertion_mode_appropriately();
+    }
+
+    // The "text" insertion mode
+    fn text(&mut self, token: HTMLToken<'a>) {
+        match token {
+            HTMLToken::Character(Character { ref data, .. }) => self.insert_characters(data),
+            HTMLToken::EndOfFile => {
+                // parse error
+                self.stack_of_open_elements.pop();
+                self.switch_to_original_insertion_mode();
+                self.reprocess_token(token);
+            }
+            HTMLToken::Tag(Tag::EndTag { .. }) => {
+                // scripts aren't run, so a script end tag is handled like any other end tag
+                self.stack_of_open_elements.pop();
+                self.switch_to_original_insertion_mode();
+            }
+            _ => {
+                // the tokenizer doesn't emit anything else while it's in a text state
+            }
+        }
+    }
+
+    fn switch_to_original_insertion_mode(&mut self) {
+        let original_insertion_mode = self
+            .original_insertion_mode
+            .take()
+            .expect("the original insertion mode should be set before switching back to it");
+        self.switch_insertion_mode(original_insertion_mode);
+    }
+
+    // The "after body" insertion mode
+    fn after_body(&mut self, token: HTMLToken<'a>) {
+        match token {
+            HTMLToken::Character(Character { ref data, .. }) if is_whitespace(data) => {
+                self.in_body(token);
+            }
+            HTMLToken::Comment(Comment { data, .. }) => {
+                // the comment goes into the html element, after the body
+                let html_element = self.stack_of_open_elements[0];
+                self.insert_comment(data, Some(html_element));
+            }
+            HTMLToken::Doctype(_) => {
+                // parse error. ignore the token
+            }
+            HTMLToken::Tag(Tag::StartTag { ref tag_name, .. }) if tag_name == "html" => {
+                self.in_body(token);
+            }
+            HTMLToken::Tag(Tag::EndTag { ref tag_name, .. }) if tag_name == "html" => {
+                self.switch_insertion_mode(InsertionMode::AfterAfterBody);
+            }
+            HTMLToken::EndOfFile => self.stop_parsing(),
+            _ => {
+                // parse error
+                self.switch_insertion_mode(InsertionMode::InBody);
+                self.reprocess_token(token);
+            }
+        }
+    }
+
+    // The "in frameset" insertion mode
+    fn in_frameset(&mut self, token: HTMLToken<'a>) {
+        match token {
+            HTMLToken::Character(Character { ref data, .. }) if is_whitespace(data) => {
+                self.insert_characters(data);
+            }
+            HTMLToken::Comment(Comment { data, .. }) => self.insert_comment(data, None),
+            HTMLToken::Tag(Tag::StartTag {
+                ref tag_name,
+                ref attributes,
+                ..
+            }) => match tag_name.as_ref() {
+                "html" => self.in_body(token),
+                "frameset" => {
+                    self.insert_html_element(tag_name, attributes.clone());
+                }
+                "frame" => {
+                    self.insert_html_element(tag_name, attributes.clone());
+                    self.stack_of_open_elements.pop();
+                }
+                "noframes" => self.in_head(token),
+                _ => {
+                    // parse error. ignore the token
+                }
+            },
+            // the root html element can't be popped
+            HTMLToken::Tag(Tag::EndTag { ref tag_name, .. })
+                if tag_name == "frameset" && self.stack_of_open_elements.len() > 1 =>
+            {
+                self.stack_of_open_elements.pop();
+                if self.node_name(self.current_node()) != "frameset" {
+                    self.switch_insertion_mode(InsertionMode::AfterFrameset);
+                }
+            }
+            HTMLToken::EndOfFile => self.stop_parsing(),
+            _ => {
+                // parse error. ignore the token
+            }
+        }
+    }
+
+    // The "after frameset" insertion mode
+    fn after_frameset(&mut self, token: HTMLToken<'a>) {
+        match token {
+            HTMLToken::Character(Character { ref data, .. }) if is_whitespace(data) => {
+                self.insert_characters(data);
+            }
+            HTMLToken::Comment(Comment { data, .. }) => self.insert_comment(data, None),
+            HTMLToken::Tag(Tag::StartTag { ref tag_name, .. }) if tag_name == "html" => {
+                self.in_body(token);
+            }
+            HTMLToken::Tag(Tag::EndTag { ref tag_name, .. }) if tag_name == "html" => {
+                self.switch_insertion_mode(InsertionMode::AfterAfterFrameset);
+            }
+            HTMLToken::Tag(Tag::StartTag { ref tag_name, .. }) if tag_name == "noframes" => {
+                self.in_head(token);
+            }
+            HTMLToken::EndOfFile => self.stop_parsing(),
+            _ => {
+                // parse error. ignore the token
+            }
+        }
+    }
+
+    // The "after after body" insertion mode
+    fn after_after_body(&mut self, token: HTMLToken<'a>) {
+        match token {
+            HTMLToken::Comment(Comment { data, .. }) => {
+                let document = self.document.document();
+                self.insert_comment(data, Some(document));
+            }
+            HTMLToken::Doctype(_) => self.in_body(token),
+            HTMLToken::Character(Character { ref data, .. }) if is_whitespace(data) => {
+                self.in_body(token);
+            }
+            HTMLToken::Tag(Tag::StartTag { ref tag_name, .. }) if tag_name == "html" => {
+                self.in_body(token);
+            }
+            HTMLToken::EndOfFile => self.stop_parsing(),
+            _ => {
+                // parse error
+                self.switch_insertion_mode(InsertionMode::InBody);
+                self.reprocess_token(token);
+            }
+        }
+    }
+
+    // The "after after frameset" insertion mode
+    fn after_after_frameset(&mut self, token: HTMLToken<'a>) {
+        match token {
+            HTMLToken::Comment(Comment { data, .. }) => {
+                let document = self.document.document();
+                self.insert_comment(data, Some(document));
+            }
+            HTMLToken::Doctype(_) => self.in_body(token),
+            HTMLToken::Character(Character { ref data, .. }) if is_whitespace(data) => {
+                self.in_body(token);
+            }
+            HTMLToken::Tag(Tag::StartTag { ref tag_name, .. }) if tag_name == "html" => {
+                self.in_body(token);
+            }
+            HTMLToken::Tag(Tag::StartTag { ref tag_name, .. }) if tag_name == "noframes" => {
+                self.in_head(token);
+            }
+            HTMLToken::EndOfFile => self.stop_parsing(),
+            _ => {
+                // parse error. ignore the token
+            }
+        }
+    }
+
+    // The "in template" insertion mode
+    fn in_template(&mut self, token: HTMLToken<'a>) {
+        match token {
+            HTMLToken::Character(_) | HTMLToken::Comment(_) | HTMLToken::Doctype(_) => {
+                self.in_body(token);
+            }
+            HTMLToken::Tag(Tag::StartTag { ref tag_name, .. }) => match tag_name.as_ref() {
+                "base" | "basefont" | "bgsound" | "link" | "meta" | "noframes" | "script"
+                | "style" | "template" | "title" => self.in_head(token),
+                // the first table part decides what the template holds, e.g. a template that
+                // starts with a <tr> is parsed like the inside of a table body
+                "caption" | "colgroup" | "tbody" | "tfoot" | "thead" => {
+                    self.switch_template_insertion_mode(InsertionMode::InTable, token);
+                }
+                "col" => self.switch_template_insertion_mode(InsertionMode::InColumnGroup, token),
+                "tr" => self.switch_template_insertion_mode(InsertionMode::InTableBody, token),
+                "td" | "th" => self.switch_template_insertion_mode(InsertionMode::InRow, token),
+                _ => self.switch_template_insertion_mode(InsertionMode::InBody, token),
+            },
+            HTMLToken::Tag(Tag::EndTag { ref tag_name, .. }) if tag_name == "template" => {
+                self.in_head(token);
+            }
+            HTMLToken::Tag(Tag::EndTag { .. }) => {
+                // parse error. ignore the token
+            }
+            HTMLToken::EndOfFile => {
+                if !self.stack_has_element("template") {
+                    self.stop_parsing();
+                    return;
+                }
+                // parse error. the template is closed and the end of the file is processed again
+                self.close_the_template();
+                self.reprocess_token(token);
+            }
+        }
+    }
+
+    /// Replaces the current template insertion mode, which is also switched to, and reprocesses
+    /// the token in it
+    fn switch_template_insertion_mode(
+        &mut self,
+        insertion_mode: InsertionMode,
+        token: HTMLToken<'a>,
+    ) {
+        self.stack_of_template_insertion_modes.pop();
+        self.stack_of_template_insertion_modes.push(insertion_mode);
+        self.switch_insertion_mode(insertion_mode);
+        self.reprocess_token(token);
+    }
+
+    /// Pops the template element and everything opened inside it, and goes back to the insertion
+    /// mode from before it
+    fn close_the_template(&mut self) {
+        self.pop_until_element_has_been_popped("template");
+        self.clear_the_list_of_active_formatting_elements_up_to_the_last_marker();
+        self.stack_of_template_insertion_modes.pop();
+        self.reset_the_insertion_mode_appropriately();
+    }
+
+    /// Works out which insertion mode to go back to from the stack of open elements, after
+    /// something like a table has been closed
+    fn reset_the_insertion_mode_appropriately(&mut self) {
+        for (index, node) in self.stack_of_open_elements.iter().enumerate().rev() {
+            let last = index == 0;
+            let insertion_mode = match self.node_name(*node) {
+                "select" => {
+                    let in_table = self.stack_of_open_elements[..index]
+                        .iter()
+                        .rev()
+                        .take_while(|ancestor| self.node_name(**ancestor) != "template")
+                        .any(|ancestor| self.node_name(*ancestor) == "table");
+                    if in_table {
+                        InsertionMode::InSelectInTable
+                    } else {
+                        InsertionMode::InSelect
+                    }
+                }
+                "template" => match self.stack_of_template_insertion_modes.last() {
+                    Some(insertion_mode) => *insertion_mode,
+                    None => continue,
+                },
+                "td" | "th" if !last => InsertionMode::InCell,
+                "tr" => InsertionMode::InRow,
+                "tbody" | "thead" | "tfoot" => InsertionMode::InTableBody,
+                "caption" => InsertionMode::InCaption,
+                "colgroup" => InsertionMode::InColumnGroup,
+                "table" => InsertionMode::InTable,
+                "head" if !last => InsertionMode::InHead,
+                "body" => InsertionMode::InBody,
+                "frameset" => InsertionMode::InFrameset,
+                "html" if self.head_element_pointer.is_none() => InsertionMode::BeforeHead,
+                "html" => InsertionMode::AfterHead,
+                _ if last => InsertionMode::InBody,
+                _ => continue,
+            };
+            self.switch_insertion_mode(insertion_mode);
+            return;
+        }
+    }
+
+    /// Pops elements until the current node is one of `context` (or the html element), e.g. to
+    /// get back to the table before starting a new row group
+    fn clear_the_stack_back_to(&mut self, context: &[&str]) {
+        while !context.contains(&self.node_name(self.current_node())) {
+            self.stack_of_open_elements.pop();
+        }
+    }
+
+    fn stop_parsing(&mut self) {
+        self.stack_of_open_elements.clear();
+        self.stopped = true;
+    }
+
+    fn current_node(&self) -> NodeId {
+        *self
+            .stack_of_open_elements
+            .last()
+            .expect("the stack of open elements shouldn't be empty")
+    }
+
+    /// The tag name of an HTML element, or an empty string for any other kind of node. SVG and
+    /// MathML elements have tag names like `title` and `a` too, but when the tree construction
+    /// rules check for a tag name they mean an HTML element with that name
+    fn node_name(&self, node: NodeId) -> &str {
+        match self.document.node(node).as_element() {
+            Some(element) if element.is_html() => &element.name,
+            _ => "",
+        }
+    }
+
+    /// Elements in the special category get different treatment from the parser, for example an
+    /// end tag can't close elements past one of them
+    fn is_special_element(&self, node: NodeId) -> bool {
+        is_special(self.node_name(node)) || self.is_one_of(node, SPECIAL_FOREIGN_ELEMENTS)
+    }
+
+    fn stack_has_element(&self, tag_name: &str) -> bool {
+        self.stack_of_open_elements
+            .iter()
+            .any(|node| self.node_name(*node) == tag_name)
+    }
+
+    fn has_an_element_in_specific_scope(
+        &self,
+        tag_name: &str,
+        scope: &[(Namespace, &str)],
+    ) -> bool {
+        for node in self.stack_of_open_elements.iter().rev() {
+            if self.node_name(*node) == tag_name {
+                return true;
+            }
+            if self.is_one_of(*node, scope) {
+                return false;
+            }
+        }
+        false
+    }
+
+    /// Whether a node is an element with one of these namespaces and tag names
+    fn is_one_of(&self, node: NodeId, elements: &[(Namespace, &str)]) -> bool {
+        self.document.node(node).as_element().is_some_and(|element| {
+            elements
+                .iter()
+                .any(|(namespace, name)| element.namespace == *namespace && element.name == *name)
+        })
+    }
+
+    /// Select scope is different from the others: every element ends it, other than `optgroup`
+    /// and `option`
+    fn has_an_element_in_select_scope(&self, tag_name: &str) -> bool {
+        for node in self.stack_of_open_elements.iter().rev() {
+            let node_name = self.node_name(*node);
+            if node_name == tag_name {
+                return true;
+            }
+            if !matches!(node_name, "optgroup" | "option") {
+                return false;
+            }
+        }
+        false
+    }
+
+    fn has_an_element_in_scope(&self, tag_name: &str) -> bool {
+        self.has_an_element_in_specific_scope(tag_name, DEFAULT_SCOPE)
+    }
+
+    fn pop_until_element_has_been_popped(&mut self, tag_name: &str) {
+        while let Some(node) = self.stack_of_open_elements.pop() {
+            if self.node_name(node) == tag_name {
+                break;
+            }
+        }
+    }
+
+    fn position_in_stack(&self, node: NodeId) -> Option<usize> {
+        self.stack_of_open_elements
+            .iter()
+            .position(|open_element| *open_element == node)
+    }
+
+    /// Like `has_an_element_in_scope`, but for one element in particular rather than any element
+    /// with a tag name
+    fn has_a_particular_element_in_scope(&self, element: NodeId) -> bool {
+        for node in self.stack_of_open_elements.iter().rev() {
+            if *node == element {
+                return true;
+            }
+            if self.is_one_of(*node, DEFAULT_SCOPE) {
+                return false;
+            }
+        }
+        false
+    }
+
+    fn position_in_the_list_of_active_formatting_elements(&self, element: NodeId) -> Option<usize> {
+        self.list_of_active_formatting_elements
+            .iter()
+            .position(|entry| matches!(entry, ActiveFormattingElement::Element(node) if *node == element))
+    }
+
+    fn is_in_the_list_of_active_formatting_elements(&self, element: NodeId) -> bool {
+        self.position_in_the_list_of_active_formatting_elements(element)
+            .is_some()
+    }
+
+    fn remove_from_the_list_of_active_formatting_elements(&mut self, element: NodeId) {
+        if let Some(index) = self.position_in_the_list_of_active_formatting_elements(element) {
+            self.list_of_active_formatting_elements.remove(index);
+        }
+    }
+
+    /// The last element with this tag name in the list of active formatting elements, unless
+    /// there's a marker after it
+    fn active_formatting_element_after_last_marker(&self, tag_name: &str) -> Option<NodeId> {
+        for entry in self.list_of_active_formatting_elements.iter().rev() {
+            match entry {
+                ActiveFormattingElement::Marker => return None,
+                ActiveFormattingElement::Element(element) if self.node_name(*element) == tag_name => {
+                    return Some(*element);
+                }
+                ActiveFormattingElement::Element(_) => {}
+            }
+        }
+        None
+    }
+
+    /// Adds a formatting element to the list. If there are already three elements after the last
+    /// marker with the same tag name and attributes, the earliest of them is dropped first (the
+    /// spec calls this the Noah's Ark clause)
+    fn push_onto_the_list_of_active_formatting_elements(&mut self, element: NodeId) {
+        let mut matching_elements = Vec::new();
+        for (index, entry) in self.list_of_active_formatting_elements.iter().enumerate().rev() {
+            match entry {
+                ActiveFormattingElement::Marker => break,
+                ActiveFormattingElement::Element(other) => {
+                    if self.is_same_formatting_element(*other, element) {
+                        matching_elements.push(index);
+                    }
+                }
+            }
+        }
+        if matching_elements.len() >= 3 {
+            let earliest = *matching_elements.last().unwrap();
+            self.list_of_active_formatting_elements.remove(earliest);
+        }
+        self.list_of_active_formatting_elements
+            .push(ActiveFormattingElement::Element(element));
+    }
+
+    /// Whether two elements have the same tag name and the same attributes, in any order
+    fn is_same_formatting_element(&self, first: NodeId, second: NodeId) -> bool {
+        let (Some(first), Some(second)) = (
+            self.document.node(first).as_element(),
+            self.document.node(second).as_element(),
+        ) else {
+            return false;
+        };
+        first.name == second.name
+            && first.attributes.len() == second.attributes.len()
+            && first
+                .attributes
+                .iter()
+                .all(|attribute| second.get_attribute(&attribute.key) == Some(attribute.value.as_ref()))
+    }
+
+    /// Reopens formatting elements that were closed implicitly, e.g. by a `</p>`, so that text
+    /// after them is still formatted: `<p><b>bold</p>still bold`
+    fn reconstruct_the_active_formatting_elements(&mut self) {
+        let is_open_or_marker = |parser: &Self, entry: &ActiveFormattingElement| match entry {
+            ActiveFormattingElement::Marker => true,
+            ActiveFormattingElement::Element(element) => parser.stack_of_open_elements.contains(element),
+        };
+        match self.list_of_active_formatting_elements.last() {
+            Some(last) if !is_open_or_marker(self, last) => {}
+            _ => return,
+        }
+
+        // rewind to the entry after the last one that's open or a marker, then create an element
+        // for every entry from there to the end of the list
+        let mut index = self.list_of_active_formatting_elements.len() - 1;
+        while index > 0 && !is_open_or_marker(self, &self.list_of_active_formatting_elements[index - 1]) {
+            index -= 1;
+        }
+        for index in index..self.list_of_active_formatting_elements.len() {
+            let ActiveFormattingElement::Element(entry) = self.list_of_active_formatting_elements[index] else {
+                continue;
+            };
+            let new_element = self.clone_element(entry);
+            self.insert_element(new_element);
+            self.list_of_active_formatting_elements[index] = ActiveFormattingElement::Element(new_element);
+        }
+    }
+
+    /// Pops elements whose end tags can be left out, like `p` and `li`, off the stack of open
+    /// elements, stopping at an element with the `except` tag name
+    fn generate_implied_end_tags(&mut self, except: Option<&str>) {
+        loop {
+            let node_name = self.node_name(self.current_node());
+            if !has_implied_end_tag(node_name) || Some(node_name) == except {
+                break;
+            }
+            self.stack_of_open_elements.pop();
+        }
+    }
+
+    /// Like `generate_implied_end_tags`, but also pops the table parts, for when the whole table
+    /// is being closed
+    fn generate_all_implied_end_tags_thoroughly(&mut self) {
+        while has_implied_end_tag_thoroughly(self.node_name(self.current_node())) {
+            self.stack_of_open_elements.pop();
+        }
+    }
+
+    fn close_a_p_element(&mut self) {
+        // if the current node isn't a p element now, this is a parse error
+        self.generate_implied_end_tags(Some("p"));
+        self.pop_until_element_has_been_popped("p");
+    }
+
+    /// Block-level elements can't go inside a paragraph, their start tags close any open one
+    fn close_a_p_element_in_button_scope(&mut self) {
+        if self.has_an_element_in_specific_scope("p", BUTTON_SCOPE) {
+            self.close_a_p_element();
+        }
+    }
+
+    /// A new `li` closes the previous `li`, and a new `dd` or `dt` closes the previous `dd` or
+    /// `dt`, unless it's in a nested list or some other special element in between
+    fn close_list_item(&mut self, tag_names: &[&str]) {
+        for node in self.stack_of_open_elements.clone().into_iter().rev() {
+            let node_name = self.node_name(node);
+            if tag_names.contains(&node_name) {
+                let node_name = node_name.to_string();
+                // if the current node isn't node_name now, this is a parse error
+                self.generate_implied_end_tags(Some(&node_name));
+                self.pop_until_element_has_been_popped(&node_name);
+                return;
+            }
+            if self.is_special_element(node) && !matches!(node_name, "address" | "div" | "p") {
+                return;
+            }
+        }
+    }
+
+    fn clear_the_list_of_active_formatting_elements_up_to_the_last_marker(&mut self) {
+        while let Some(entry) = self.list_of_active_formatting_elements.pop() {
+            if let ActiveFormattingElement::Marker = entry {
+                break;
+            }
+        }
+    }
+
+    fn add_missing_attributes(&mut self, element: NodeId, new_attributes: &Attributes<'a>) {
+        if let Some(element) = self.document.node_mut(element).as_element_mut() {
+            for new_attribute in new_attributes {
+                if element.get_attribute(&new_attribute.key).is_none() {
+                    element.attributes.push(new_attribute.clone().into_owned());
+                }
+            }
+        }
+    }
+
+    /// Title and textarea (RCDATA), style, xmp, iframe, noembed and noframes (RAWTEXT) and
+    /// script (script data) elements only contain text, which the tokenizer is switched over to
+    fn parse_generic_text_element(
+        &mut self,
+        tag_name: &str,
+        attributes: Attributes<'a>,
+        tokenizer_state: HTMLTokenizerState,
+    ) {
+        self.insert_html_element(tag_name, attributes);
+        self.tokenizer.switch_state(tokenizer_state);
+        self.original_insertion_mode = Some(self.insertion_mode);
+        self.switch_insertion_mode(InsertionMode::Text);
+    }
+
+    fn appropriate_place_for_inserting_a_node(&self, override_target: Option<NodeId>) -> InsertionLocation {
+        let target = override_target.unwrap_or_else(|| self.current_node());
+        let location = if self.foster_parenting
+            && matches!(self.node_name(target), "table" | "tbody" | "tfoot" | "thead" | "tr")
+        {
+            self.foster_parenting_location()
+        } else {
+            InsertionLocation {
+                parent: target,
+                before: None,
+            }
+        };
+
+        // nodes inside a template go in its contents instead
+        match self.document.template_contents(location.parent) {
+            Some(contents) => InsertionLocation {
+                parent: contents,
+                before: None,
+            },
+            None => location,
+        }
+    }
+
+    /// Where foster parented nodes go: right before the last table on the stack of open elements,
+    /// or into a template that was opened after that table
+    fn foster_parenting_location(&self) -> InsertionLocation {
+        let last_table = self
+            .stack_of_open_elements
+            .iter()
+            .rposition(|node| self.node_name(*node) == "table");
+        let last_template = self
+            .stack_of_open_elements
+            .iter()
+            .rposition(|node| self.node_name(*node) == "template");
+        if let Some(last_template_index) = last_template {
+            if last_table.is_none_or(|last_table_index| last_template_index > last_table_index) {
+                return InsertionLocation {
+                    parent: self.stack_of_open_elements[last_template_index],
+                    before: None,
+                };
+            }
+        }
+        let Some(last_table_index) = last_table else {
+            return InsertionLocation {
+                parent: self.stack_of_open_elements[0],
+                before: None,
+            };
+        };
+        let last_table = self.stack_of_open_elements[last_table_index];
+        match self.document.parent(last_table) {
+            Some(parent) => InsertionLocation {
+                parent,
+                before: Some(last_table),
+            },
+            // the table isn't in the tree anymore
+            None => InsertionLocation {
+                parent: self.stack_of_open_elements[last_table_index - 1],
+                before: None,
+            },
+        }
+    }
+
+    fn create_element(&mut self, tag_name: &str, attributes: Attributes<'a>) -> NodeId {
+        self.document
+            .create_node(NodeKind::Element(Element::new(tag_name, attributes)))
+    }
+
+    /// Creates a new element with the same tag name and attributes as `element`, which is what
+    /// the spec means by creating an element for the token `element` was created for
+    fn clone_element(&mut self, element: NodeId) -> NodeId {
+        let kind = self.document.node(element).kind.clone();
+        self.document.create_node(kind)
+    }
+
+    /// Creates an element, inserts it where it should go and pushes it onto the stack of open
+    /// elements so that it becomes the current node
+    fn insert_html_element(&mut self, tag_name: &str, attributes: Attributes<'a>) -> NodeId {
+        let element = self.create_element(tag_name, attributes);
+        self.insert_element(element);
+        element
+    }
+
+    /// Like `insert_html_element`, for an svg or math element
+    fn insert_foreign_element(
+        &mut self,
+        tag_name: &str,
+        namespace: Namespace,
+        attributes: Attributes<'a>,
+    ) -> NodeId {
+        let element = self.document.create_node(NodeKind::Element(Element::new_in_namespace(
+            tag_name, namespace, attributes,
+        )));
+        self.insert_element(element);
+        element
+    }
+
+    /// Inserts an element that has already been created and pushes it onto the stack of open
+    /// elements
+    fn insert_element(&mut self, element: NodeId) {
+        let location = self.appropriate_place_for_inserting_a_node(None);
+        self.document
+            .insert_before(location.parent, element, location.before);
+        self.stack_of_open_elements.push(element);
+    }
+
+    /// Inserts characters into the tree, adding them to the text node right before the insertion
+    /// point if there is one
+    fn insert_characters(&mut self, data: &str) {
+        let location = self.appropriate_place_for_inserting_a_node(None);
+        if location.parent == self.document.document() {
+            // text can't be inserted into the document node itself
+            return;
+        }
+
+        let previous_sibling = match location.before {
+            Some(before) => self.document.node(before).previous_sibling(),
+            None => self.document.node(location.parent).last_child(),
+        };
+        if let Some(previous_sibling) = previous_sibling {
+            if let NodeKind::Text(text) = &mut self.document.node_mut(previous_sibling).kind {
+                text.push_str(data);
+                return;
+            }
+        }
+
+        let text = self
+            .document
+            .create_node(NodeKind::Text(data.to_string()));
+        self.document
+            .insert_before(location.parent, text, location.before);
+    }
+
+    /// Inserts a comment as the last child of `parent`, or wherever nodes normally go if no
+    /// parent is given
+    fn insert_comment(&mut self, data: Cow<str>, parent: Option<NodeId>) {
+        let location = match parent {
+            Some(parent) => InsertionLocation { parent, before: None },
+            None => self.appropriate_place_for_inserting_a_node(None),
+        };
+        let comment = self.document.create_node(NodeKind::Comment(data.into_owned()));
+        self.document
+            .insert_before(location.parent, comment, location.before);
+    }
+}
+
+// the elements that end a scope, see has_an_element_in_specific_scope. SVG and MathML elements
+// that can have HTML inside them end most scopes too
+const DEFAULT_SCOPE: &[(Namespace, &str)] = &[
+    (Namespace::Html, "applet"),
+    (Namespace::Html, "caption"),
+    (Namespace::Html, "html"),
+    (Namespace::Html, "table"),
+    (Namespace::Html, "td"),
+    (Namespace::Html, "th"),
+    (Namespace::Html, "marquee"),
+    (Namespace::Html, "object"),
+    (Namespace::Html, "template"),
+    (Namespace::MathML, "mi"),
+    (Namespace::MathML, "mo"),
+    (Namespace::MathML, "mn"),
+    (Namespace::MathML, "ms"),
+    (Namespace::MathML, "mtext"),
+    (Namespace::MathML, "annotation-xml"),
+    (Namespace::Svg, "foreignObject"),
+    (Namespace::Svg, "desc"),
+    (Namespace::Svg, "title"),
+];
+
+const LIST_ITEM_SCOPE: &[(Namespace, &str)] = &[
+    (Namespace::Html, "applet"),
+    (Namespace::Html, "caption"),
+    (Namespace::Html, "html"),
+    (Namespace::Html, "table"),
+    (Namespace::Html, "td"),
+    (Namespace::Html, "th"),
+    (Namespace::Html, "marquee"),
+    (Namespace::Html, "object"),
+    (Namespace::Html, "template"),
+    (Namespace::Html, "ol"),
+    (Namespace::Html, "ul"),
+    (Namespace::MathML, "mi"),
+    (Namespace::MathML, "mo"),
+    (Namespace::MathML, "mn"),
+    (Namespace::MathML, "ms"),
+    (Namespace::MathML, "mtext"),
+    (Namespace::MathML, "annotation-xml"),
+    (Namespace::Svg, "foreignObject"),
+    (Namespace::Svg, "desc"),
+    (Namespace::Svg, "title"),
+];
+
+const BUTTON_SCOPE: &[(Namespace, &str)] = &[
+    (Namespace::Html, "applet"),
+    (Namespace::Html, "caption"),
+    (Namespace::Html, "html"),
+    (Namespace::Html, "table"),
+    (Namespace::Html, "td"),
+    (Namespace::Html, "th"),
+    (Namespace::Html, "marquee"),
+    (Namespace::Html, "object"),
+    (Namespace::Html, "template"),
+    (Namespace::Html, "button"),
+    (Namespace::MathML, "mi"),
+    (Namespace::MathML, "mo"),
+    (Namespace::MathML, "mn"),
+    (Namespace::MathML, "ms"),
+    (Namespace::MathML, "mtext"),
+    (Namespace::MathML, "annotation-xml"),
+    (Namespace::Svg, "foreignObject"),
+    (Namespace::Svg, "desc"),
+    (Namespace::Svg, "title"),
+];
+
+const TABLE_SCOPE: &[(Namespace, &str)] = &[
+    (Namespace::Html, "html"),
+    (Namespace::Html, "table"),
+    (Namespace::Html, "template"),
+];
+
+// the elements the stack of open elements is cleared back to, see clear_the_stack_back_to
+const TABLE_CONTEXT: &[&str] = &["table", "template", "html"];
+const TABLE_BODY_CONTEXT: &[&str] = &["tbody", "tfoot", "thead", "template", "html"];
+const TABLE_ROW_CONTEXT: &[&str] = &["tr", "template", "html"];
+
+const SPECIAL_FOREIGN_ELEMENTS: &[(Namespace, &str)] = &[
+    (Namespace::MathML, "mi"),
+    (Namespace::MathML, "mo"),
+    (Namespace::MathML, "mn"),
+    (Namespace::MathML, "ms"),
+    (Namespace::MathML, "mtext"),
+    (Namespace::MathML, "annotation-xml"),
+    (Namespace::Svg, "foreignObject"),
+    (Namespace::Svg, "desc"),
+    (Namespace::Svg, "title"),
+];
+
+const MATHML_TEXT_INTEGRATION_POINTS: &[(Namespace, &str)] = &[
+    (Namespace::MathML, "mi"),
+    (Namespace::MathML, "mo"),
+    (Namespace::MathML, "mn"),
+    (Namespace::MathML, "ms"),
+    (Namespace::MathML, "mtext"),
+];
+
+/// Start tags that can't be in foreign content, they close the svg or math element they're in.
+/// `font` only does this with a color, face or size attribute
+fn breaks_out_of_foreign_content(tag_name: &str) -> bool {
+    matches!(
+        tag_name,
+        "b" | "big" | "blockquote" | "body" | "br" | "center" | "code" | "dd" | "div" | "dl"
+            | "dt" | "em" | "embed" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "head" | "hr"
+            | "i" | "img" | "li" | "listing" | "menu" | "meta" | "nobr" | "ol" | "p" | "pre"
+            | "ruby" | "s" | "small" | "span" | "strong" | "strike" | "sub" | "sup" | "table"
+            | "tt" | "u" | "ul" | "var"
+    )
+}
+
+/// Table parts that close a select in a table, when they're opened or closed
+fn is_table_part_closing_a_select(tag_name: &str) -> bool {
+    matches!(
+        tag_name,
+        "caption" | "table" | "tbody" | "tfoot" | "thead" | "tr" | "td" | "th"
+    )
+}
+
+fn is_heading(tag_name: &str) -> bool {
+    matches!(tag_name, "h1" | "h2" | "h3" | "h4" | "h5" | "h6")
+}
+
+/// Elements whose end tag can be left out when the parent element is closed, see
+/// https://html.spec.whatwg.org/multipage/parsing.html#generate-implied-end-tags
+fn has_implied_end_tag(tag_name: &str) -> bool {
+    matches!(
+        tag_name,
+        "dd" | "dt" | "li" | "optgroup" | "option" | "p" | "rb" | "rp" | "rt" | "rtc"
+    )
+}
+
+fn has_implied_end_tag_thoroughly(tag_name: &str) -> bool {
+    has_implied_end_tag(tag_name)
+        || matches!(
+            tag_name,
+            "caption" | "colgroup" | "tbody" | "td" | "tfoot" | "th" | "thead" | "tr"
+        )
+}
+
+/// The HTML elements in the special category, see `HTMLParser::is_special_element`
+fn is_special(tag_name: &str) -> bool {
+    matches!(
+        tag_name,
+        "address" | "applet" | "area" | "article" | "aside" | "base" | "basefont" | "bgsound"
+            | "blockquote" | "body" | "br" | "button" | "caption" | "center" | "col"
+            | "colgroup" | "dd" | "details" | "dir" | "div" | "dl" | "dt" | "embed"
+            | "fieldset" | "figcaption" | "figure" | "footer" | "form" | "frame" | "frameset"
+            | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "head" | "header" | "hgroup" | "hr"
+            | "html" | "iframe" | "img" | "input" | "keygen" | "li" | "link" | "listing"
+            | "main" | "marquee" | "menu" | "meta" | "nav" | "noembed" | "noframes"
+            | "noscript" | "object" | "ol" | "p" | "param" | "plaintext" | "pre" | "script"
+            | "search" | "section" | "select" | "source" | "style" | "summary" | "table"
+            | "tbody" | "td" | "template" | "textarea" | "tfoot" | "th" | "thead" | "title"
+            | "tr" | "track" | "ul" | "wbr" | "xmp"
+    )
+}
+
+fn is_whitespace_character(character: char) -> bool {
+    matches!(character, '\t' | '\n' | '\x0C' | '\r' | ' ')
+}
+
+fn is_whitespace(data: &str) -> bool {
+    data.chars().all(is_whitespace_character)
+}
+
@@ -0,0 +1,126 @@
+use super::DOCTYPE;
+use crate::dom::QuirksMode;
+
+/// Public identifiers that put the document in quirks mode when the DOCTYPE's public identifier
+/// starts with them (compared ignoring ASCII case)
+const QUIRKY_PUBLIC_IDENTIFIER_PREFIXES: &[&str] = &[
+    "+//Silmaril//dtd html Pro v0r11 19970101//",
+    "-//AS//DTD HTML 3.0 asWedit + extensions//",
+    "-//AdvaSoft Ltd//DTD HTML 3.0 asWedit + extensions//",
+    "-//IETF//DTD HTML 2.0 Level 1//",
+    "-//IETF//DTD HTML 2.0 Level 2//",
+    "-//IETF//DTD HTML 2.0 Strict Level 1//",
+    "-//IETF//DTD HTML 2.0 Strict Level 2//",
+    "-//IETF//DTD HTML 2.0 Strict//",
+    "-//IETF//DTD HTML 2.0//",
+    "-//IETF//DTD HTML 2.1E//",
+    "-//IETF//DTD HTML 3.0//",
+    "-//IETF//DTD HTML 3.2 Final//",
+    "-//IETF//DTD HTML 3.2//",
+    "-//IETF//DTD HTML 3//",
+    "-//IETF//DTD HTML Level 0//",
+    "-//IETF//DTD HTML Level 1//",
+    "-//IETF//DTD HTML Level 2//",
+    "-//IETF//DTD HTML Level 3//",
+    "-//IETF//DTD HTML Strict Level 0//",
+    "-//IETF//DTD HTML Strict Level 1//",
+    "-//IETF//DTD HTML Strict Level 2//",
+    "-//IETF//DTD HTML Strict Level 3//",
+    "-//IETF//DTD HTML Strict//",
+    "-//IETF//DTD HTML//",
+    "-//Metrius//DTD Metrius Presentational//",
+    "-//Microsoft//DTD Internet Explorer 2.0 HTML Strict//",
+    "-//Microsoft//DTD Internet Explorer 2.0 HTML//",
+    "-//Microsoft//DTD Internet Explorer 2.0 Tables//",
+    "-//Microsoft//DTD Internet Explorer 3.0 HTML Strict//",
+    "-//Microsoft//DTD Internet Explorer 3.0 HTML//",
+    "-//Microsoft//DTD Internet Explorer 3.0 Tables//",
+    "-//Netscape Comm. Corp.//DTD HTML//",
+    "-//Netscape Comm. Corp.//DTD Strict HTML//",
+    "-//O'Reilly and Associates//DTD HTML 2.0//",
+    "-//O'Reilly and Associates//DTD HTML Extended 1.0//",
+    "-//O'Reilly and Associates//DTD HTML Extended Relaxed 1.0//",
+    "-//SQ//DTD HTML 2.0 HoTMetaL + extensions//",
+    "-//SoftQuad Software//DTD HoTMetaL PRO 6.0::19990601::extensions to HTML 4.0//",
+    "-//SoftQuad//DTD HoTMetaL PRO 4.0::19971010::extensions to HTML 4.0//",
+    "-//Spyglass//DTD HTML 2.0 Extended//",
+    "-//Sun Microsystems Corp.//DTD HotJava HTML//",
+    "-//Sun Microsystems Corp.//DTD HotJava Strict HTML//",
+    "-//W3C//DTD HTML 3 1995-03-24//",
+    "-//W3C//DTD HTML 3.2 Draft//",
+    "-//W3C//DTD HTML 3.2 Final//",
+    "-//W3C//DTD HTML 3.2//",
+    "-//W3C//DTD HTML 3.2S Draft//",
+    "-//W3C//DTD HTML 4.0 Frameset//",
+    "-//W3C//DTD HTML 4.0 Transitional//",
+    "-//W3C//DTD HTML Experimental 19960712//",
+    "-//W3C//DTD HTML Experimental 970421//",
+    "-//W3C//DTD W3 HTML//",
+    "-//W3O//DTD W3 HTML 3.0//",
+    "-//WebTechs//DTD Mozilla HTML 2.0//",
+    "-//WebTechs//DTD Mozilla HTML//",
+];
+
+/// Public identifiers that put the document in quirks mode when the DOCTYPE's public identifier
+/// is exactly one of them (compared ignoring ASCII case)
+const QUIRKY_PUBLIC_IDENTIFIERS: &[&str] = &[
+    "-//W3O//DTD W3 HTML Strict 3.0//EN//",
+    "-/W3C/DTD HTML 4.0 Transitional/EN",
+    "HTML",
+];
+
+const QUIRKY_SYSTEM_IDENTIFIER: &str = "http://www.ibm.com/data/dtd/v11/ibmxhtml1-transitional.dtd";
+
+/// HTML 4.01 frameset and transitional doctypes are quirky without a system identifier, and only
+/// limited-quirky with one
+const HTML_4_01_PUBLIC_IDENTIFIER_PREFIXES: &[&str] = &[
+    "-//W3C//DTD HTML 4.01 Frameset//",
+    "-//W3C//DTD HTML 4.01 Transitional//",
+];
+
+const LIMITED_QUIRKY_PUBLIC_IDENTIFIER_PREFIXES: &[&str] = &[
+    "-//W3C//DTD XHTML 1.0 Frameset//",
+    "-//W3C//DTD XHTML 1.0 Transitional//",
+];
+
+/// Works out which mode a document with this DOCTYPE should be rendered in, following the rules
+/// for DOCTYPE tokens in the "initial" insertion mode:
+/// https://html.spec.whatwg.org/multipage/parsing.html#the-initial-insertion-mode
+pub fn quirks_mode(doctype: &DOCTYPE) -> QuirksMode {
+    let public_identifier = doctype.public_identifier.as_deref();
+    let system_identifier = doctype.system_identifier.as_deref();
+    let public_identifier_starts_with = |prefixes: &[&str]| {
+        public_identifier.is_some_and(|public_identifier| {
+            prefixes
+                .iter()
+                .any(|prefix| starts_with_ignoring_ascii_case(public_identifier, prefix))
+        })
+    };
+
+    if doctype.force_quirks
+        || doctype.name.as_deref() != Some("html")
+        || public_identifier.is_some_and(|public_identifier| {
+            QUIRKY_PUBLIC_IDENTIFIERS
+                .iter()
+                .any(|quirky| public_identifier.eq_ignore_ascii_case(quirky))
+        })
+        || system_identifier
+            .is_some_and(|system_identifier| system_identifier.eq_ignore_ascii_case(QUIRKY_SYSTEM_IDENTIFIER))
+        || public_identifier_starts_with(QUIRKY_PUBLIC_IDENTIFIER_PREFIXES)
+        || (system_identifier.is_none() && public_identifier_starts_with(HTML_4_01_PUBLIC_IDENTIFIER_PREFIXES))
+    {
+        QuirksMode::Quirks
+    } else if public_identifier_starts_with(LIMITED_QUIRKY_PUBLIC_IDENTIFIER_PREFIXES)
+        || (system_identifier.is_some() && public_identifier_starts_with(HTML_4_01_PUBLIC_IDENTIFIER_PREFIXES))
+    {
+        QuirksMode::LimitedQuirks
+    } else {
+        QuirksMode::NoQuirks
+    }
+}
+
+fn starts_with_ignoring_ascii_case(string: &str, prefix: &str) -> bool {
+    string.len() >= prefix.len()
+        && string.is_char_boundary(prefix.len())
+        && string[..prefix.len()].eq_ignore_ascii_case(prefix)
+}
@@ -0,0 +1,479 @@
+use std::fmt;
+
+use crate::alice::{Attribute, Attributes, DOCTYPE};
+
+/// A handle to a node in a `Document`. Nodes are stored in an arena owned by the document, so a
+/// NodeId is only meaningful for the document that created it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+/// The document and every node in it. Nodes are never freed: a node that is taken out of the
+/// tree keeps its NodeId and can be inserted again later
+#[derive(Debug)]
+pub struct Document {
+    nodes: Vec<Node>,
+    quirks_mode: QuirksMode,
+}
+
+/// Which mode the document is rendered in. Documents without a DOCTYPE, or with one from before
+/// HTML5, are rendered in quirks mode, which copies old browser bugs that legacy pages depend on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QuirksMode {
+    #[default]
+    NoQuirks,
+    LimitedQuirks,
+    Quirks,
+}
+
+/// A node and its links to the nodes around it. The links can only be changed through the
+/// `Document`, which keeps them consistent with each other
+#[derive(Debug)]
+pub struct Node {
+    parent: Option<NodeId>,
+    first_child: Option<NodeId>,
+    last_child: Option<NodeId>,
+    previous_sibling: Option<NodeId>,
+    next_sibling: Option<NodeId>,
+    pub kind: NodeKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum NodeKind {
+    Document,
+    /// Holds the contents of a template element, see `Element::template_contents`
+    DocumentFragment,
+    DocumentType(DocumentType),
+    Element(Element),
+    Text(String),
+    Comment(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentType {
+    pub name: String,
+    pub public_id: String,
+    pub system_id: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Element {
+    pub name: String,
+    pub namespace: Namespace,
+    pub attributes: Attributes<'static>,
+    /// The document fragment a template element's contents go in. They aren't children of the
+    /// template, so they don't show up when walking the tree. Set by `Document::create_node`
+    pub template_contents: Option<NodeId>,
+}
+
+/// The namespace an element (or attribute) is in. Elements are HTML elements unless they're
+/// inside an `<svg>` or `<math>` element
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Namespace {
+    Html,
+    MathML,
+    Svg,
+    XLink,
+    Xml,
+    XmlNs,
+}
+
+impl Namespace {
+    pub fn url(&self) -> &'static str {
+        match self {
+            Namespace::Html => "http://www.w3.org/1999/xhtml",
+            Namespace::MathML => "http://www.w3.org/1998/Math/MathML",
+            Namespace::Svg => "http://www.w3.org/2000/svg",
+            Namespace::XLink => "http://www.w3.org/1999/xlink",
+            Namespace::Xml => "http://www.w3.org/XML/1998/namespace",
+            Namespace::XmlNs => "http://www.w3.org/2000/xmlns/",
+        }
+    }
+
+    /// The prefix the html5lib tests print namespaced elements and attributes with, like
+    /// `<svg circle>` or `xlink href`
+    fn prefix(&self) -> &'static str {
+        match self {
+            Namespace::Html => "",
+            Namespace::MathML => "math",
+            Namespace::Svg => "svg",
+            Namespace::XLink => "xlink",
+            Namespace::Xml => "xml",
+            Namespace::XmlNs => "xmlns",
+        }
+    }
+}
+
+/// A DOCTYPE token's missing name and identifiers become empty strings on the node
+impl From<DOCTYPE<'_>> for DocumentType {
+    fn from(doctype: DOCTYPE<'_>) -> Self {
+        Self {
+            name: doctype.name.unwrap_or_default().into_owned(),
+            public_id: doctype.public_identifier.unwrap_or_default().into_owned(),
+            system_id: doctype.system_identifier.unwrap_or_default().into_owned(),
+        }
+    }
+}
+
+impl Element {
+    /// Creates an HTML element. The attributes are copied out of the tokens they came from,
+    /// elements outlive the input
+    pub fn new(name: &str, attributes: Attributes<'_>) -> Self {
+        Self::new_in_namespace(name, Namespace::Html, attributes)
+    }
+
+    pub fn new_in_namespace(name: &str, namespace: Namespace, attributes: Attributes<'_>) -> Self {
+        Self {
+            name: name.to_string(),
+            namespace,
+            attributes: attributes.into_iter().map(Attribute::into_owned).collect(),
+            template_contents: None,
+        }
+    }
+
+    /// The value of the attribute with this name, not counting namespaced attributes like
+    /// `xlink:href`
+    pub fn get_attribute(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.namespace.is_none() && attribute.key == key)
+            .map(|attribute| attribute.value.as_ref())
+    }
+
+    pub fn is_html(&self) -> bool {
+        self.namespace == Namespace::Html
+    }
+}
+
+impl Node {
+    fn new(kind: NodeKind) -> Self {
+        Self {
+            parent: None,
+            first_child: None,
+            last_child: None,
+            previous_sibling: None,
+            next_sibling: None,
+            kind,
+        }
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn first_child(&self) -> Option<NodeId> {
+        self.first_child
+    }
+
+    pub fn last_child(&self) -> Option<NodeId> {
+        self.last_child
+    }
+
+    pub fn previous_sibling(&self) -> Option<NodeId> {
+        self.previous_sibling
+    }
+
+    pub fn next_sibling(&self) -> Option<NodeId> {
+        self.next_sibling
+    }
+
+    pub fn as_element(&self) -> Option<&Element> {
+        match &self.kind {
+            NodeKind::Element(element) => Some(element),
+            _ => None,
+        }
+    }
+
+    pub fn as_element_mut(&mut self) -> Option<&mut Element> {
+        match &mut self.kind {
+            NodeKind::Element(element) => Some(element),
+            _ => None,
+        }
+    }
+
+    /// The tag name of the node if it is an element
+    pub fn element_name(&self) -> Option<&str> {
+        self.as_element().map(|element| element.name.as_str())
+    }
+}
+
+impl Default for Document {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Document {
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![Node::new(NodeKind::Document)],
+            quirks_mode: QuirksMode::NoQuirks,
+        }
+    }
+
+    pub fn quirks_mode(&self) -> QuirksMode {
+        self.quirks_mode
+    }
+
+    pub fn set_quirks_mode(&mut self, quirks_mode: QuirksMode) {
+        self.quirks_mode = quirks_mode;
+    }
+
+    /// The document node itself, the root of the tree
+    pub fn document(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0]
+    }
+
+    pub fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        &mut self.nodes[id.0]
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.node(id).parent
+    }
+
+    pub fn first_child(&self, id: NodeId) -> Option<NodeId> {
+        self.node(id).first_child
+    }
+
+    pub fn next_sibling(&self, id: NodeId) -> Option<NodeId> {
+        self.node(id).next_sibling
+    }
+
+    /// The children of a node, first to last
+    pub fn children(&self, id: NodeId) -> Children<'_> {
+        Children {
+            document: self,
+            next: self.node(id).first_child,
+        }
+    }
+
+    /// Every node under a node (but not the node itself) in tree order, which is the order their
+    /// start tags appear in the source
+    pub fn descendants(&self, id: NodeId) -> Descendants<'_> {
+        Descendants {
+            document: self,
+            root: id,
+            next: self.node(id).first_child,
+        }
+    }
+
+    /// Creates a node that isn't in the tree yet. A template element gets an empty document
+    /// fragment for its contents along with it
+    pub fn create_node(&mut self, kind: NodeKind) -> NodeId {
+        let is_template = matches!(
+            &kind,
+            NodeKind::Element(element) if element.is_html() && element.name == "template"
+        );
+        self.nodes.push(Node::new(kind));
+        let id = NodeId(self.nodes.len() - 1);
+        if is_template {
+            let contents = self.create_node(NodeKind::DocumentFragment);
+            if let Some(element) = self.node_mut(id).as_element_mut() {
+                element.template_contents = Some(contents);
+            }
+        }
+        id
+    }
+
+    /// The document fragment holding a template element's contents
+    pub fn template_contents(&self, id: NodeId) -> Option<NodeId> {
+        self.node(id).as_element().and_then(|element| element.template_contents)
+    }
+
+    pub fn append_child(&mut self, parent: NodeId, child: NodeId) {
+        self.insert_before(parent, child, None);
+    }
+
+    /// Inserts `child` into `parent` right before `reference`, or as the last child of `parent`
+    /// if there is no reference. A child that is already in the tree is moved
+    pub fn insert_before(&mut self, parent: NodeId, child: NodeId, reference: Option<NodeId>) {
+        self.remove_from_parent(child);
+
+        let previous_sibling = match reference {
+            Some(reference) => self.node(reference).previous_sibling,
+            None => self.node(parent).last_child,
+        };
+
+        let node = self.node_mut(child);
+        node.parent = Some(parent);
+        node.previous_sibling = previous_sibling;
+        node.next_sibling = reference;
+
+        match previous_sibling {
+            Some(previous_sibling) => self.node_mut(previous_sibling).next_sibling = Some(child),
+            None => self.node_mut(parent).first_child = Some(child),
+        }
+        match reference {
+            Some(reference) => self.node_mut(reference).previous_sibling = Some(child),
+            None => self.node_mut(parent).last_child = Some(child),
+        }
+    }
+
+    /// Takes a node out of the tree. The node (and everything under it) still exists and can be
+    /// inserted somewhere else
+    pub fn remove_from_parent(&mut self, child: NodeId) {
+        let Some(parent) = self.node(child).parent else {
+            return;
+        };
+        let previous_sibling = self.node(child).previous_sibling;
+        let next_sibling = self.node(child).next_sibling;
+
+        match previous_sibling {
+            Some(previous_sibling) => self.node_mut(previous_sibling).next_sibling = next_sibling,
+            None => self.node_mut(parent).first_child = next_sibling,
+        }
+        match next_sibling {
+            Some(next_sibling) => self.node_mut(next_sibling).previous_sibling = previous_sibling,
+            None => self.node_mut(parent).last_child = previous_sibling,
+        }
+
+        let node = self.node_mut(child);
+        node.parent = None;
+        node.previous_sibling = None;
+        node.next_sibling = None;
+    }
+}
+
+/// Iterator over the children of a node, see `Document::children`
+pub struct Children<'a> {
+    document: &'a Document,
+    next: Option<NodeId>,
+}
+
+impl Iterator for Children<'_> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next?;
+        self.next = self.document.node(current).next_sibling;
+        Some(current)
+    }
+}
+
+/// Iterator over the descendants of a node, see `Document::descendants`
+pub struct Descendants<'a> {
+    document: &'a Document,
+    root: NodeId,
+    next: Option<NodeId>,
+}
+
+impl Iterator for Descendants<'_> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next?;
+        let document = self.document;
+        let root = self.root;
+
+        // go down into the children first. Without any, go across to the next sibling, climbing
+        // back up towards the root until some ancestor has one
+        self.next = document.node(current).first_child.or_else(|| {
+            let mut node = current;
+            loop {
+                if node == root {
+                    return None;
+                }
+                if let Some(next_sibling) = document.node(node).next_sibling {
+                    return Some(next_sibling);
+                }
+                node = document.node(node).parent?;
+            }
+        });
+        Some(current)
+    }
+}
+
+/// Prints the tree in the format used by the html5lib tree construction tests, which makes it
+/// easy to compare against what browsers produce
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "#document")?;
+
+        // an explicit stack of the nodes left to print and their depth, rather than recursion, so
+        // that deeply nested documents can't overflow the call stack. Popping a node pushes its
+        // next sibling, then its first child, then its template contents, which come first
+        let mut stack: Vec<(NodeId, usize)> = Vec::new();
+        stack.extend(self.node(self.document()).first_child.map(|child| (child, 0)));
+        while let Some((id, depth)) = stack.pop() {
+            let node = self.node(id);
+            stack.extend(node.next_sibling.map(|sibling| (sibling, depth)));
+            stack.extend(node.first_child.map(|child| (child, depth + 1)));
+            if let Some(contents) = node.as_element().and_then(|element| element.template_contents) {
+                stack.push((contents, depth + 1));
+            }
+            self.fmt_node(f, id, depth)?;
+        }
+        Ok(())
+    }
+}
+
+impl Document {
+    /// Prints a single node, without its children, indented two spaces for every level of depth
+    fn fmt_node(&self, f: &mut fmt::Formatter<'_>, id: NodeId, depth: usize) -> fmt::Result {
+        match &self.node(id).kind {
+            NodeKind::Document => {}
+            NodeKind::DocumentType(doctype) => {
+                write_indent(f, depth)?;
+                if doctype.public_id.is_empty() && doctype.system_id.is_empty() {
+                    writeln!(f, "<!DOCTYPE {}>", doctype.name)?;
+                } else {
+                    writeln!(
+                        f,
+                        "<!DOCTYPE {} \"{}\" \"{}\">",
+                        doctype.name, doctype.public_id, doctype.system_id
+                    )?;
+                }
+            }
+            NodeKind::Element(element) => {
+                write_indent(f, depth)?;
+                writeln!(f, "<{}>", qualified_name(Some(element.namespace), &element.name))?;
+                let mut attributes: Vec<_> = element
+                    .attributes
+                    .iter()
+                    .map(|attribute| (qualified_name(attribute.namespace, &attribute.key), &attribute.value))
+                    .collect();
+                attributes.sort();
+                for (name, value) in attributes {
+                    write_indent(f, depth + 1)?;
+                    writeln!(f, "{}=\"{}\"", name, value)?;
+                }
+            }
+            NodeKind::DocumentFragment => {
+                write_indent(f, depth)?;
+                writeln!(f, "content")?;
+            }
+            NodeKind::Text(data) => {
+                write_indent(f, depth)?;
+                writeln!(f, "\"{}\"", data)?;
+            }
+            NodeKind::Comment(data) => {
+                write_indent(f, depth)?;
+                writeln!(f, "<!-- {} -->", data)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The start of a line in the html5lib tree format, `| ` followed by two spaces per level of
+/// depth. Written a piece at a time, since a formatting width can't be as large as the depth of
+/// a deeply nested document
+fn write_indent(f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+    f.write_str("| ")?;
+    for _ in 0..depth {
+        f.write_str("  ")?;
+    }
+    Ok(())
+}
+
+/// A name with its namespace's prefix in front, separated by a space, e.g. `svg foreignObject`
+fn qualified_name(namespace: Option<Namespace>, name: &str) -> String {
+    match namespace {
+        Some(namespace) if namespace != Namespace::Html => format!("{} {}", namespace.prefix(), name),
+        _ => name.to_string(),
+    }
+}
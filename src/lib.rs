@@ -0,0 +1,2 @@
+pub mod alice;
+pub mod dom;
@@ -1,13 +1,15 @@
 use std::fs;
-mod alice;
+use wonder_land::alice;
 
 fn main() {
-    let result = fs::read_to_string("test_5.html");
+    let result = fs::read("test_5.html");
     match result {
         Ok(content) => {
             // here's where the main code is;
-            let mut tokenizer = alice::HTMLTokenizer::new(&content.chars().collect());
-            tokenizer.run();
+            let tokenizer = alice::HTMLTokenizer::from_bytes(&content, None);
+            let parser = alice::HTMLParser::new(tokenizer);
+            let document = parser.parse();
+            print!("{}", document);
         }
         Err(_err) => {
             println!("probably couldn't read the file");
@@ -0,0 +1,146 @@
+//! Tests that token text borrows from the input, and is only copied when the tokenizer had to
+//! change it: character references, NUL replacement, newline normalization and lowercasing.
+
+use std::borrow::Cow;
+
+use wonder_land::alice::{Attribute, Character, Comment, HTMLToken, HTMLTokenizer, Tag};
+
+// it has to be a Cow, which variant it is is the whole point
+#[allow(clippy::ptr_arg)]
+fn is_borrowed(text: &Cow<'_, str>) -> bool {
+    matches!(text, Cow::Borrowed(_))
+}
+
+fn character_data<'a>(tokens: &'a [HTMLToken<'_>]) -> Vec<&'a Cow<'a, str>> {
+    tokens
+        .iter()
+        .filter_map(|token| match token {
+            HTMLToken::Character(Character { data, .. }) => Some(data),
+            _ => None,
+        })
+        .collect()
+}
+
+fn start_tag<'a>(tokens: &'a [HTMLToken<'_>]) -> (&'a Cow<'a, str>, &'a [Attribute<'a>]) {
+    tokens
+        .iter()
+        .find_map(|token| match token {
+            HTMLToken::Tag(Tag::StartTag {
+                tag_name,
+                attributes,
+                ..
+            }) => Some((tag_name, attributes.as_slice())),
+            _ => None,
+        })
+        .expect("there should be a start tag")
+}
+
+#[test]
+fn plain_text_is_borrowed() {
+    let tokens: Vec<_> = HTMLTokenizer::new("hello world<p>text</p>").collect();
+    let data = character_data(&tokens);
+    assert_eq!(data, ["hello world", "text"]);
+    assert!(data.iter().all(|data| is_borrowed(data)));
+}
+
+#[test]
+fn tag_names_and_attributes_are_borrowed() {
+    let tokens: Vec<_> = HTMLTokenizer::new("<p class=\"a b\" id=x data-y='z'></p>").collect();
+    let (tag_name, attributes) = start_tag(&tokens);
+    assert_eq!(tag_name, "p");
+    assert!(is_borrowed(tag_name));
+    for attribute in attributes {
+        assert!(is_borrowed(&attribute.key), "{:?}", attribute.key);
+        assert!(is_borrowed(&attribute.value), "{:?}", attribute.value);
+    }
+
+    let HTMLToken::Tag(Tag::EndTag { tag_name, .. }) = &tokens[1] else {
+        panic!("expected an end tag, got {:?}", tokens[1]);
+    };
+    assert!(is_borrowed(tag_name));
+}
+
+#[test]
+fn comments_and_doctype_names_are_borrowed() {
+    let tokens: Vec<_> = HTMLTokenizer::new("<!DOCTYPE html><!-- c -->").collect();
+    let HTMLToken::Doctype(doctype) = &tokens[0] else {
+        panic!("expected a DOCTYPE, got {:?}", tokens[0]);
+    };
+    assert!(doctype.name.as_ref().is_some_and(is_borrowed));
+    let HTMLToken::Comment(Comment { data, .. }) = &tokens[1] else {
+        panic!("expected a comment, got {:?}", tokens[1]);
+    };
+    assert!(is_borrowed(data));
+}
+
+#[test]
+fn a_lone_ampersand_is_still_borrowed() {
+    let tokens: Vec<_> = HTMLTokenizer::new("a & b<p title=a&b>").collect();
+    assert!(is_borrowed(character_data(&tokens)[0]));
+    let (_, attributes) = start_tag(&tokens);
+    assert_eq!(attributes[0].value, "a&b");
+    assert!(is_borrowed(&attributes[0].value));
+}
+
+#[test]
+fn character_references_are_owned() {
+    let tokens: Vec<_> = HTMLTokenizer::new("a&amp;b<p title='x&amp;y'>").collect();
+    let data = character_data(&tokens);
+    assert_eq!(data, ["a&b"]);
+    assert!(matches!(data[0], Cow::Owned(_)));
+
+    let (tag_name, attributes) = start_tag(&tokens);
+    assert!(is_borrowed(tag_name));
+    assert!(is_borrowed(&attributes[0].key));
+    assert_eq!(attributes[0].value, "x&y");
+    assert!(matches!(attributes[0].value, Cow::Owned(_)));
+}
+
+#[test]
+fn normalized_newlines_are_owned() {
+    let tokens: Vec<_> = HTMLTokenizer::new("a\r\nb<p title='x\r\ny'>").collect();
+    let data = character_data(&tokens);
+    assert_eq!(data, ["a\nb"]);
+    assert!(matches!(data[0], Cow::Owned(_)));
+
+    let (_, attributes) = start_tag(&tokens);
+    assert_eq!(attributes[0].value, "x\ny");
+    assert!(matches!(attributes[0].value, Cow::Owned(_)));
+}
+
+#[test]
+fn replaced_null_characters_are_owned() {
+    let tokens: Vec<_> = HTMLTokenizer::new("<!-- x\0 -->").collect();
+    let HTMLToken::Comment(Comment { data, .. }) = &tokens[0] else {
+        panic!("expected a comment, got {:?}", tokens[0]);
+    };
+    assert_eq!(data, " x\u{FFFD} ");
+    assert!(matches!(data, Cow::Owned(_)));
+}
+
+#[test]
+fn lowercased_names_are_owned() {
+    let tokens: Vec<_> = HTMLTokenizer::new("<DIV CLASS=A>").collect();
+    let (tag_name, attributes) = start_tag(&tokens);
+    assert_eq!(tag_name, "div");
+    assert!(matches!(tag_name, Cow::Owned(_)));
+    assert!(matches!(attributes[0].key, Cow::Owned(_)));
+    // the value keeps its case, so it's still borrowed
+    assert!(is_borrowed(&attributes[0].value));
+}
+
+#[test]
+fn utf_8_bytes_are_borrowed() {
+    let bytes = "caf\u{E9}<p>".as_bytes();
+    let tokens: Vec<_> = HTMLTokenizer::from_bytes(bytes, None).collect();
+    assert!(is_borrowed(character_data(&tokens)[0]));
+    assert!(is_borrowed(start_tag(&tokens).0));
+}
+
+#[test]
+fn decoded_bytes_are_owned() {
+    let tokens: Vec<_> = HTMLTokenizer::from_bytes(b"caf\xE9<p>", None).collect();
+    let data = character_data(&tokens);
+    assert_eq!(data, ["caf\u{E9}"]);
+    assert!(matches!(data[0], Cow::Owned(_)));
+}
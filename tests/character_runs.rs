@@ -0,0 +1,208 @@
+//! Tests for the fast path that passes runs of plain text along in one go, in the data state,
+//! quoted attribute values and comments. The runs are broken up by everything the tokenizer has
+//! to handle one character at a time (CR, NUL, control characters and noncharacters), and the
+//! tokens, spans and parse errors have to come out the same as if there were no fast path.
+
+use wonder_land::alice::{
+    Attribute, Character, Comment, HTMLToken, HTMLTokenizer, InitialState, ParseError,
+    ParseErrorReport, SourcePosition, SourceSpan, Tag,
+};
+
+/// Plain text with one, two, three and four byte characters in it
+const FILLER: &str = "lorem ipsum caf\u{E9} \u{20AC}5 \u{1F600}\t";
+
+/// The characters the fast path stops at, each put between long runs of filler
+const INTERRUPTIONS: &[&str] = &[
+    "\r\n", "\r", "\0", "\u{80}", "\u{85}", "\u{9F}", "\u{FDD0}", "\u{1}", "\r\r\n", "\u{FFFF}",
+];
+
+fn long_text() -> String {
+    let mut text = FILLER.repeat(50);
+    for interruption in INTERRUPTIONS {
+        text.push_str(interruption);
+        text.push_str(&FILLER.repeat(50));
+    }
+    text
+}
+
+fn normalize_newlines(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// Where the character at `offset` in the raw input is, counting a CRLF as one line break
+fn position_at(input: &str, offset: usize) -> SourcePosition {
+    SourcePosition {
+        offset,
+        ..SourcePosition::default().advance_over(&normalize_newlines(&input[..offset]))
+    }
+}
+
+/// The parse errors the characters in `text` cause, `text` being at `start` in `input`
+fn expected_errors(input: &str, start: usize, text: &str) -> Vec<ParseErrorReport> {
+    text.char_indices()
+        .filter_map(|(index, character)| {
+            let error = match character {
+                '\0' => ParseError::UnexpectedNullCharacter,
+                '\u{1}' | '\u{80}'..='\u{9F}' => ParseError::ControlCharacterInInputStream,
+                '\u{FDD0}' | '\u{FFFF}' => ParseError::NoncharacterInInputStream,
+                _ => return None,
+            };
+            Some(ParseErrorReport {
+                error,
+                position: position_at(input, start + index),
+            })
+        })
+        .collect()
+}
+
+fn tokenize(input: &str) -> (Vec<HTMLToken<'_>>, Vec<ParseErrorReport>) {
+    let mut tokenizer = HTMLTokenizer::new(input);
+    let mut tokens = Vec::new();
+    while let Some(token) = tokenizer.next_token() {
+        tokens.push(token);
+    }
+    let errors = tokenizer.parse_errors().to_vec();
+    (tokens, errors)
+}
+
+#[test]
+fn data_state() {
+    let input = long_text();
+    let (tokens, errors) = tokenize(&input);
+
+    let HTMLToken::Character(Character { data, span }) = &tokens[0] else {
+        panic!("expected text, got {:?}", tokens[0]);
+    };
+    // NUL is passed along as it is in the data state
+    assert_eq!(data, &normalize_newlines(&input));
+    assert_eq!(
+        *span,
+        SourceSpan {
+            start: SourcePosition::default(),
+            end: position_at(&input, input.len()),
+        }
+    );
+    assert!(matches!(tokens[1], HTMLToken::EndOfFile));
+    assert_eq!(errors, expected_errors(&input, 0, &input));
+}
+
+#[test]
+fn data_state_matches_the_character_by_character_rcdata_state() {
+    // the RCDATA state has no fast path, and treats everything here the same way as the data
+    // state other than NUL
+    let input = long_text().replace('\0', "");
+    let (data_tokens, data_errors) = tokenize(&input);
+
+    let mut tokenizer = HTMLTokenizer::new(&input);
+    tokenizer.set_initial_state(InitialState::RCDATA);
+    let mut rcdata_tokens = Vec::new();
+    while let Some(token) = tokenizer.next_token() {
+        rcdata_tokens.push(token);
+    }
+
+    // tokens don't implement PartialEq, but their Debug output has every field in it
+    assert_eq!(format!("{:?}", data_tokens), format!("{:?}", rcdata_tokens));
+    assert_eq!(data_errors, tokenizer.parse_errors());
+}
+
+#[test]
+fn data_state_runs_end_at_markup() {
+    let text = long_text();
+    let input = format!("{}<p>{}&amp;{}", text, text, text);
+    let (tokens, errors) = tokenize(&input);
+
+    let HTMLToken::Character(Character { data, span }) = &tokens[0] else {
+        panic!("expected text, got {:?}", tokens[0]);
+    };
+    assert_eq!(data, &normalize_newlines(&text));
+    assert_eq!(span.end, position_at(&input, text.len()));
+
+    let HTMLToken::Tag(Tag::StartTag { span, .. }) = &tokens[1] else {
+        panic!("expected a start tag, got {:?}", tokens[1]);
+    };
+    assert_eq!(span.start, position_at(&input, text.len()));
+
+    let HTMLToken::Character(Character { data, span }) = &tokens[2] else {
+        panic!("expected text, got {:?}", tokens[2]);
+    };
+    assert_eq!(data, &normalize_newlines(&format!("{}&{}", text, text)));
+    assert_eq!(span.start, position_at(&input, text.len() + 3));
+    assert_eq!(span.end, position_at(&input, input.len()));
+
+    let second_text = text.len() + 3;
+    let third_text = second_text + text.len() + 5;
+    let mut expected = expected_errors(&input, 0, &text);
+    expected.extend(expected_errors(&input, second_text, &text));
+    expected.extend(expected_errors(&input, third_text, &text));
+    assert_eq!(errors, expected);
+}
+
+#[test]
+fn quoted_attribute_values() {
+    let text = long_text();
+    for quote in ['"', '\''] {
+        let input = format!("<p title={}{}{} id=x>", quote, text, quote);
+        let (tokens, errors) = tokenize(&input);
+
+        let HTMLToken::Tag(Tag::StartTag { attributes, span, .. }) = &tokens[0] else {
+            panic!("expected a start tag, got {:?}", tokens[0]);
+        };
+        let Attribute {
+            value, value_span, ..
+        } = &attributes[0];
+        // NUL is replaced in attribute values
+        assert_eq!(value, &normalize_newlines(&text).replace('\0', "\u{FFFD}"));
+        let value_start = "<p title='".len();
+        assert_eq!(
+            *value_span,
+            Some(SourceSpan {
+                start: position_at(&input, value_start),
+                end: position_at(&input, value_start + text.len()),
+            })
+        );
+        assert_eq!(attributes[1].value, "x");
+        assert_eq!(span.end, position_at(&input, input.len()));
+        assert_eq!(errors, expected_errors(&input, value_start, &text));
+    }
+}
+
+#[test]
+fn comments() {
+    let text = long_text();
+    let input = format!("<!--{}-->after", text);
+    let (tokens, errors) = tokenize(&input);
+
+    let HTMLToken::Comment(Comment { data, span }) = &tokens[0] else {
+        panic!("expected a comment, got {:?}", tokens[0]);
+    };
+    assert_eq!(data, &normalize_newlines(&text).replace('\0', "\u{FFFD}"));
+    let comment_end = input.len() - "after".len();
+    assert_eq!(
+        *span,
+        SourceSpan {
+            start: SourcePosition::default(),
+            end: position_at(&input, comment_end),
+        }
+    );
+
+    let HTMLToken::Character(Character { data, span }) = &tokens[1] else {
+        panic!("expected text, got {:?}", tokens[1]);
+    };
+    assert_eq!(data, "after");
+    assert_eq!(span.start, position_at(&input, comment_end));
+    assert_eq!(errors, expected_errors(&input, 4, &text));
+}
+
+#[test]
+fn comments_with_dashes_in_them() {
+    let text = long_text();
+    let input = format!("<!--{}-{}--{}-->", text, text, text);
+    let (tokens, errors) = tokenize(&input);
+
+    let HTMLToken::Comment(Comment { data, .. }) = &tokens[0] else {
+        panic!("expected a comment, got {:?}", tokens[0]);
+    };
+    let expected = format!("{}-{}--{}", text, text, text);
+    assert_eq!(data, &normalize_newlines(&expected).replace('\0', "\u{FFFD}"));
+    assert_eq!(errors, expected_errors(&input, 4, &expected));
+}
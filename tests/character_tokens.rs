@@ -0,0 +1,151 @@
+//! Tests that contiguous character data comes out as a single `Character` token, however many
+//! pieces the tokenizer read it in (plain text, character references, newlines that had to be
+//! normalized), and that the run is flushed before whatever token ends it.
+
+use wonder_land::alice::{
+    Character, Comment, HTMLToken, HTMLTokenizer, InitialState, SourcePosition, SourceSpan, Tag,
+};
+
+/// A short description of every token, enough to check what was emitted and in what order
+fn describe(tokens: &[HTMLToken<'_>]) -> Vec<String> {
+    tokens
+        .iter()
+        .map(|token| match token {
+            HTMLToken::Character(Character { data, .. }) => format!("text {:?}", data),
+            HTMLToken::Tag(Tag::StartTag { tag_name, .. }) => format!("<{}>", tag_name),
+            HTMLToken::Tag(Tag::EndTag { tag_name, .. }) => format!("</{}>", tag_name),
+            HTMLToken::Comment(Comment { data, .. }) => format!("comment {:?}", data),
+            HTMLToken::Doctype(_) => "doctype".to_string(),
+            HTMLToken::EndOfFile => "end of file".to_string(),
+        })
+        .collect()
+}
+
+fn tokens(input: &str) -> Vec<String> {
+    let tokens: Vec<_> = HTMLTokenizer::new(input).collect();
+    describe(&tokens)
+}
+
+#[test]
+fn text_and_character_references_are_one_token() {
+    assert_eq!(
+        tokens("fish &amp; chips &lt;3 &#169;&#x1F600; &notit"),
+        ["text \"fish & chips <3 \u{A9}\u{1F600} \u{AC}it\"", "end of file"]
+    );
+}
+
+#[test]
+fn normalized_newlines_are_part_of_the_run() {
+    assert_eq!(
+        tokens("a\r\nb\rc\n\r\nd"),
+        ["text \"a\\nb\\nc\\n\\nd\"", "end of file"]
+    );
+    assert_eq!(
+        tokens("\r\n&amp;\r"),
+        ["text \"\\n&\\n\"", "end of file"]
+    );
+}
+
+#[test]
+fn markup_that_turns_out_to_be_text_is_part_of_the_run() {
+    // a < that doesn't start a tag, and a stray </ with nothing after it
+    assert_eq!(tokens("1 < 2 & 3 </"), ["text \"1 < 2 & 3 </\"", "end of file"]);
+}
+
+#[test]
+fn runs_are_flushed_before_tags() {
+    assert_eq!(
+        tokens("a&amp;b<p>c\r\nd</p>e"),
+        [
+            "text \"a&b\"",
+            "<p>",
+            "text \"c\\nd\"",
+            "</p>",
+            "text \"e\"",
+            "end of file"
+        ]
+    );
+}
+
+#[test]
+fn runs_are_flushed_before_comments_and_doctypes() {
+    assert_eq!(
+        tokens("a<!DOCTYPE html>b&gt;<!-- c -->d"),
+        [
+            "text \"a\"",
+            "doctype",
+            "text \"b>\"",
+            "comment \" c \"",
+            "text \"d\"",
+            "end of file"
+        ]
+    );
+}
+
+#[test]
+fn runs_are_flushed_before_the_end_of_file() {
+    let mut tokenizer = HTMLTokenizer::new("<p>a&amp;");
+    assert!(matches!(tokenizer.next_token(), Some(HTMLToken::Tag(_))));
+    let Some(HTMLToken::Character(Character { data, .. })) = tokenizer.next_token() else {
+        panic!("the text should come out before the end of file");
+    };
+    assert_eq!(data, "a&");
+    assert!(matches!(tokenizer.next_token(), Some(HTMLToken::EndOfFile)));
+    assert!(tokenizer.next_token().is_none());
+}
+
+#[test]
+fn no_empty_runs_between_tokens() {
+    assert_eq!(
+        tokens("<p></p><!---->"),
+        ["<p>", "</p>", "comment \"\"", "end of file"]
+    );
+}
+
+#[test]
+fn rcdata_runs_include_end_tags_that_dont_close_it() {
+    let mut tokenizer = HTMLTokenizer::new("a&amp;b</b>c</title>d");
+    tokenizer.set_initial_state(InitialState::RCDATA);
+    tokenizer.set_last_start_tag_name("title");
+    let tokens: Vec<_> = tokenizer.collect();
+    assert_eq!(
+        describe(&tokens),
+        ["text \"a&b</b>c\"", "</title>", "text \"d\"", "end of file"]
+    );
+}
+
+#[test]
+fn the_span_covers_the_whole_run() {
+    let tokens: Vec<_> = HTMLTokenizer::new("a&amp;\r\nb<br>").collect();
+    let HTMLToken::Character(Character { span, .. }) = &tokens[0] else {
+        panic!("expected text, got {:?}", tokens[0]);
+    };
+    assert_eq!(
+        *span,
+        SourceSpan {
+            start: SourcePosition::default(),
+            end: SourcePosition {
+                line: 2,
+                column: 2,
+                offset: 9,
+            },
+        }
+    );
+}
+
+#[test]
+fn whitespace_runs_can_be_split_off() {
+    let mut tokenizer = HTMLTokenizer::new(" \n a&amp;b \t<p>");
+    tokenizer.set_split_whitespace_runs(true);
+    let tokens: Vec<_> = tokenizer.collect();
+    assert_eq!(
+        describe(&tokens),
+        [
+            "text \" \\n \"",
+            "text \"a&b\"",
+            "text \" \\t\"",
+            "<p>",
+            "end of file"
+        ]
+    );
+}
@@ -0,0 +1,195 @@
+//! Tests for the encoding sniffing algorithm and the decoders behind `HTMLTokenizer::from_bytes`.
+//!
+//! The html5lib harnesses only ever hand the tokenizer a `&str`, so this is the only place byte
+//! streams get tested.
+
+use wonder_land::alice::{
+    sniff_encoding, Character, Confidence, Encoding, HTMLToken, HTMLTokenizer, SniffedEncoding,
+};
+
+fn certain(encoding: Encoding) -> SniffedEncoding {
+    SniffedEncoding {
+        encoding,
+        confidence: Confidence::Certain,
+    }
+}
+
+fn tentative(encoding: Encoding) -> SniffedEncoding {
+    SniffedEncoding {
+        encoding,
+        confidence: Confidence::Tentative,
+    }
+}
+
+/// All the text the tokenizer emits for some bytes, joined together
+fn text(bytes: &[u8], transport_layer_encoding: Option<&str>) -> String {
+    HTMLTokenizer::from_bytes(bytes, transport_layer_encoding)
+        .filter_map(|token| match token {
+            HTMLToken::Character(Character { data, .. }) => Some(data.into_owned()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn byte_order_marks_are_certain() {
+    assert_eq!(sniff_encoding(b"\xEF\xBB\xBFabc", None), certain(Encoding::Utf8));
+    assert_eq!(sniff_encoding(b"\xFF\xFEa\x00", None), certain(Encoding::Utf16LE));
+    assert_eq!(sniff_encoding(b"\xFE\xFF\x00a", None), certain(Encoding::Utf16BE));
+}
+
+#[test]
+fn byte_order_marks_are_not_part_of_the_text() {
+    assert_eq!(text(b"\xEF\xBB\xBFh\xC3\xA9", None), "h\u{E9}");
+    assert_eq!(text(b"\xFF\xFEh\x00\xE9\x00", None), "h\u{E9}");
+    assert_eq!(text(b"\xFE\xFF\x00h\x00\xE9", None), "h\u{E9}");
+}
+
+#[test]
+fn byte_order_mark_wins_over_the_transport_layer() {
+    assert_eq!(
+        sniff_encoding(b"\xEF\xBB\xBFabc", Some("windows-1252")),
+        certain(Encoding::Utf8)
+    );
+    assert_eq!(
+        sniff_encoding(b"\xFF\xFEa\x00", Some("utf-8")),
+        certain(Encoding::Utf16LE)
+    );
+}
+
+#[test]
+fn transport_layer_wins_over_meta() {
+    let bytes = b"<meta charset=\"utf-8\">caf\xE9";
+    assert_eq!(sniff_encoding(bytes, Some("latin1")), certain(Encoding::Windows1252));
+    assert_eq!(text(bytes, Some("latin1")), "caf\u{E9}");
+}
+
+#[test]
+fn unknown_transport_layer_label_is_ignored() {
+    assert_eq!(
+        sniff_encoding(b"<meta charset=windows-1252>", Some("no-such-encoding")),
+        tentative(Encoding::Windows1252)
+    );
+}
+
+#[test]
+fn meta_charset() {
+    assert_eq!(
+        sniff_encoding(b"<!DOCTYPE html><html><head><meta charset=\"windows-1252\">", None),
+        tentative(Encoding::Windows1252)
+    );
+    // the name and value are matched without caring about case
+    assert_eq!(
+        sniff_encoding(b"<META CHARSET='UTF-8'>", None),
+        tentative(Encoding::Utf8)
+    );
+}
+
+#[test]
+fn meta_inside_comments_and_other_tags_is_skipped() {
+    assert_eq!(
+        sniff_encoding(b"<!-- <meta charset=windows-1252> --><p>a", None),
+        tentative(Encoding::Utf8)
+    );
+    assert_eq!(
+        sniff_encoding(b"<div title='<meta charset=windows-1252>'>", None),
+        tentative(Encoding::Utf8)
+    );
+}
+
+#[test]
+fn meta_is_only_looked_for_in_the_first_1024_bytes() {
+    let meta = b"<meta charset=windows-1252>";
+
+    let mut bytes = vec![b'a'; 1024 - meta.len()];
+    bytes.extend_from_slice(meta);
+    assert_eq!(sniff_encoding(&bytes, None), tentative(Encoding::Windows1252));
+
+    let mut bytes = vec![b'a'; 1024];
+    bytes.extend_from_slice(meta);
+    assert_eq!(sniff_encoding(&bytes, None), tentative(Encoding::Utf8));
+}
+
+#[test]
+fn meta_http_equiv_content_type() {
+    assert_eq!(
+        sniff_encoding(
+            b"<meta http-equiv=\"Content-Type\" content=\"text/html; charset=windows-1252\">",
+            None
+        ),
+        tentative(Encoding::Windows1252)
+    );
+    assert_eq!(
+        sniff_encoding(b"<meta content='text/html;charset=\"latin1\"' http-equiv=content-type>", None),
+        tentative(Encoding::Windows1252)
+    );
+    // content without the pragma doesn't count
+    assert_eq!(
+        sniff_encoding(b"<meta content=\"text/html; charset=windows-1252\">", None),
+        tentative(Encoding::Utf8)
+    );
+}
+
+#[test]
+fn first_of_charset_and_content_wins() {
+    assert_eq!(
+        sniff_encoding(
+            b"<meta charset=windows-1252 http-equiv=content-type content=\"text/html; charset=utf-8\">",
+            None
+        ),
+        tentative(Encoding::Windows1252)
+    );
+    assert_eq!(
+        sniff_encoding(
+            b"<meta http-equiv=content-type content=\"text/html; charset=windows-1252\" charset=utf-8>",
+            None
+        ),
+        tentative(Encoding::Windows1252)
+    );
+}
+
+#[test]
+fn meta_utf_16_means_utf_8() {
+    assert_eq!(sniff_encoding(b"<meta charset=utf-16le>", None), tentative(Encoding::Utf8));
+}
+
+#[test]
+fn falls_back_to_windows_1252_for_invalid_utf_8() {
+    assert_eq!(sniff_encoding(b"caf\xC3\xA9", None), tentative(Encoding::Utf8));
+    assert_eq!(sniff_encoding(b"caf\xE9", None), tentative(Encoding::Windows1252));
+    assert_eq!(text(b"caf\xE9", None), "caf\u{E9}");
+}
+
+#[test]
+fn windows_1252_decoding() {
+    let decoded = Encoding::Windows1252.decode(b"\x80\x85\x8A\x91\x92\x99\x9F");
+    assert_eq!(decoded, "\u{20AC}\u{2026}\u{0160}\u{2018}\u{2019}\u{2122}\u{0178}");
+
+    // bytes that windows-1252 leaves undefined stay the C1 control with the same value
+    let decoded = Encoding::Windows1252.decode(b"\x81\x8D\x8F\x90\x9D");
+    assert_eq!(decoded, "\u{81}\u{8D}\u{8F}\u{90}\u{9D}");
+
+    // the top half is the same as Latin-1
+    assert_eq!(Encoding::Windows1252.decode(b"\xA0\xE9\xFF"), "\u{A0}\u{E9}\u{FF}");
+}
+
+#[test]
+fn odd_byte_count_in_utf_16() {
+    assert_eq!(Encoding::Utf16LE.decode(b"a\x00b"), "a\u{FFFD}");
+    assert_eq!(Encoding::Utf16BE.decode(b"\x00a\x00"), "a\u{FFFD}");
+    assert_eq!(text(b"\xFF\xFEa\x00b", None), "a\u{FFFD}");
+}
+
+#[test]
+fn unpaired_surrogates_in_utf_16() {
+    assert_eq!(Encoding::Utf16LE.decode(b"\x00\xD8a\x00"), "\u{FFFD}a");
+}
+
+#[test]
+fn tokenizer_reports_the_sniffed_encoding() {
+    let tokenizer = HTMLTokenizer::from_bytes(b"<meta charset=latin1>", None);
+    assert_eq!(tokenizer.encoding(), Some(tentative(Encoding::Windows1252)));
+
+    let tokenizer = HTMLTokenizer::new("<p>");
+    assert_eq!(tokenizer.encoding(), None);
+}
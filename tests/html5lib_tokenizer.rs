@@ -0,0 +1,242 @@
+//! Runs the tokenizer tests in tests/html5lib-tests/tokenizer, which use the format of the
+//! html5lib-tests suite (https://github.com/html5lib/html5lib-tests/tree/master/tokenizer).
+//!
+//! Every `.test` file in that directory is picked up, so upstream files can be dropped in as they
+//! are. Each test is run once for every one of its initial states, and the emitted tokens and parse
+//! error codes are compared with the expected ones. Run with `cargo test --test html5lib_tokenizer
+//! -- --nocapture` to see how many tests passed in each file.
+//!
+//! Tests that are known to fail go in `KNOWN_FAILURES` rather than being edited out of the files,
+//! so the files stay the same as upstream's.
+
+use std::fs;
+use std::path::Path;
+
+use serde_json::{json, Map, Value};
+use wonder_land::alice::{DOCTYPE, HTMLToken, HTMLTokenizer, InitialState, Tag};
+
+const TEST_DIRECTORY: &str = "tests/html5lib-tests/tokenizer";
+
+/// Tests that are skipped because the tokenizer doesn't pass them yet, by file name and
+/// description. Each entry should say why, and be taken out once the test passes
+const KNOWN_FAILURES: &[(&str, &str)] = &[];
+
+#[test]
+fn html5lib_tokenizer_tests() {
+    let mut paths: Vec<_> = fs::read_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join(TEST_DIRECTORY))
+        .expect("the html5lib tokenizer tests should be in tests/html5lib-tests/tokenizer")
+        .map(|entry| entry.expect("the test directory should be readable").path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "test"))
+        .collect();
+    paths.sort();
+
+    let mut failures = Vec::new();
+    let (mut total_passed, mut total_failed, mut total_skipped) = (0, 0, 0);
+    for path in paths {
+        let file_name = path.file_name().unwrap().to_string_lossy().into_owned();
+        let contents = fs::read_to_string(&path).unwrap();
+        let file: Value = serde_json::from_str(&contents)
+            .unwrap_or_else(|error| panic!("{} isn't valid json: {}", file_name, error));
+
+        let (mut passed, mut failed, mut skipped) = (0, 0, 0);
+        for test in file["tests"].as_array().into_iter().flatten() {
+            let description = test["description"].as_str().unwrap_or_default();
+            if KNOWN_FAILURES.contains(&(file_name.as_str(), description)) {
+                skipped += 1;
+                continue;
+            }
+            for outcome in run_test(test) {
+                match outcome {
+                    Outcome::Passed => passed += 1,
+                    Outcome::Skipped => skipped += 1,
+                    Outcome::Failed(failure) => {
+                        failed += 1;
+                        failures.push(format!("{}: {}", file_name, failure));
+                    }
+                }
+            }
+        }
+        println!(
+            "{:<32} {:>4} passed {:>4} failed {:>4} skipped",
+            file_name, passed, failed, skipped
+        );
+        total_passed += passed;
+        total_failed += failed;
+        total_skipped += skipped;
+    }
+    println!(
+        "{:<32} {:>4} passed {:>4} failed {:>4} skipped",
+        "total", total_passed, total_failed, total_skipped
+    );
+
+    assert!(
+        failures.is_empty(),
+        "{} html5lib tokenizer tests failed:\n\n{}",
+        failures.len(),
+        failures.join("\n\n")
+    );
+}
+
+enum Outcome {
+    Passed,
+    Failed(String),
+    // the test needs something a Rust string can't hold, like a lone surrogate
+    Skipped,
+}
+
+/// Runs a test once for each of its initial states, the data state if it doesn't list any
+fn run_test(test: &Value) -> Vec<Outcome> {
+    let description = test["description"].as_str().unwrap_or_default();
+    let double_escaped = test["doubleEscaped"].as_bool().unwrap_or(false);
+    let initial_states = match test["initialStates"].as_array() {
+        Some(states) => states.iter().filter_map(Value::as_str).collect(),
+        None => vec!["Data state"],
+    };
+
+    let input = test["input"].as_str().unwrap_or_default();
+    let input = if double_escaped { unescape(input) } else { Some(input.to_string()) };
+    let expected_output = if double_escaped {
+        unescape_strings(&test["output"])
+    } else {
+        Some(test["output"].clone())
+    };
+    let (Some(input), Some(expected_output)) = (input, expected_output) else {
+        return initial_states.iter().map(|_| Outcome::Skipped).collect();
+    };
+    let expected_output = coalesce_characters(expected_output.as_array().unwrap());
+    let expected_errors: Vec<&str> = test["errors"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|error| error["code"].as_str())
+        .collect();
+
+    initial_states
+        .into_iter()
+        .map(|initial_state| {
+            let mut tokenizer = HTMLTokenizer::new(&input);
+            tokenizer.set_initial_state(initial_state_named(initial_state));
+            if let Some(last_start_tag) = test["lastStartTag"].as_str() {
+                tokenizer.set_last_start_tag_name(last_start_tag);
+            }
+
+            let mut output = Vec::new();
+            while let Some(token) = tokenizer.next_token() {
+                if let Some(token) = token_to_json(token) {
+                    output.push(token);
+                }
+            }
+            let output = coalesce_characters(&output);
+            let errors: Vec<&str> = tokenizer
+                .parse_errors()
+                .iter()
+                .map(|report| report.error.code())
+                .collect();
+
+            if output == expected_output && errors == expected_errors {
+                Outcome::Passed
+            } else {
+                Outcome::Failed(format!(
+                    "{} ({})\n  input:    {:?}\n  expected: {} {:?}\n  actual:   {} {:?}",
+                    description,
+                    initial_state,
+                    input,
+                    Value::Array(expected_output.clone()),
+                    expected_errors,
+                    Value::Array(output),
+                    errors
+                ))
+            }
+        })
+        .collect()
+}
+
+fn initial_state_named(name: &str) -> InitialState {
+    match name {
+        "Data state" => InitialState::Data,
+        "PLAINTEXT state" => InitialState::PLAINTEXT,
+        "RCDATA state" => InitialState::RCDATA,
+        "RAWTEXT state" => InitialState::RAWTEXT,
+        "Script data state" => InitialState::ScriptData,
+        "CDATA section state" => InitialState::CDATASection,
+        _ => panic!("unknown initial state {:?}", name),
+    }
+}
+
+/// A token the way html5lib-tests writes it. The end-of-file token isn't written out
+fn token_to_json(token: HTMLToken<'_>) -> Option<Value> {
+    match token {
+        HTMLToken::Doctype(DOCTYPE {
+            name,
+            public_identifier,
+            system_identifier,
+            force_quirks,
+            ..
+        }) => Some(json!(["DOCTYPE", name, public_identifier, system_identifier, !force_quirks])),
+        HTMLToken::Tag(Tag::StartTag {
+            tag_name,
+            self_closing,
+            attributes,
+            ..
+        }) => {
+            let attributes: Map<String, Value> = attributes
+                .into_iter()
+                .map(|attribute| (attribute.key.into_owned(), json!(attribute.value)))
+                .collect();
+            if self_closing {
+                Some(json!(["StartTag", tag_name, attributes, true]))
+            } else {
+                Some(json!(["StartTag", tag_name, attributes]))
+            }
+        }
+        HTMLToken::Tag(Tag::EndTag { tag_name, .. }) => Some(json!(["EndTag", tag_name])),
+        HTMLToken::Comment(comment) => Some(json!(["Comment", comment.data])),
+        HTMLToken::Character(character) => Some(json!(["Character", character.data])),
+        HTMLToken::EndOfFile => None,
+    }
+}
+
+/// Joins consecutive character tokens, html5lib-tests doesn't care where the text is split
+fn coalesce_characters(tokens: &[Value]) -> Vec<Value> {
+    let mut coalesced: Vec<Value> = Vec::new();
+    for token in tokens {
+        if token[0] == "Character" {
+            if let Some(previous) = coalesced.last_mut().filter(|previous| previous[0] == "Character") {
+                let data = format!("{}{}", previous[1].as_str().unwrap(), token[1].as_str().unwrap());
+                previous[1] = Value::String(data);
+                continue;
+            }
+        }
+        coalesced.push(token.clone());
+    }
+    coalesced
+}
+
+/// Tests with `doubleEscaped` set have their input and output escaped once more, with `\uXXXX`
+/// sequences that serde_json has left alone. Returns None if that gives a lone surrogate
+fn unescape(string: &str) -> Option<String> {
+    let mut code_units = Vec::new();
+    let mut rest = string;
+    while let Some(index) = rest.find("\\u") {
+        code_units.extend(rest[..index].encode_utf16());
+        code_units.push(u16::from_str_radix(rest.get(index + 2..index + 6)?, 16).ok()?);
+        rest = &rest[index + 6..];
+    }
+    code_units.extend(rest.encode_utf16());
+    // surrogate pairs are written as two escapes, so they're decoded together
+    String::from_utf16(&code_units).ok()
+}
+
+fn unescape_strings(value: &Value) -> Option<Value> {
+    Some(match value {
+        Value::String(string) => Value::String(unescape(string)?),
+        Value::Array(values) => Value::Array(values.iter().map(unescape_strings).collect::<Option<_>>()?),
+        Value::Object(entries) => Value::Object(
+            entries
+                .iter()
+                .map(|(key, value)| Some((unescape(key)?, unescape_strings(value)?)))
+                .collect::<Option<_>>()?,
+        ),
+        value => value.clone(),
+    })
+}
@@ -0,0 +1,177 @@
+//! Runs the tree construction tests in tests/html5lib-tests/tree-construction, which use the
+//! format of the html5lib-tests suite
+//! (https://github.com/html5lib/html5lib-tests/tree/master/tree-construction).
+//!
+//! Every `.dat` file in that directory is picked up. Each test is parsed as a whole document and
+//! the tree is compared with the expected one. Parse errors aren't compared, the tree construction
+//! stage doesn't report them, they're only counted so it shows how many go unchecked. Fragment
+//! tests and tests that need scripting turned on are skipped. Run with `cargo test --test
+//! html5lib_tree_construction -- --nocapture` to see how many tests passed in each file.
+//!
+//! Tests that are known to fail go in `KNOWN_FAILURES` rather than being edited out of the files,
+//! so the files stay the same as upstream's.
+
+use std::fs;
+use std::path::Path;
+
+use wonder_land::alice::{HTMLParser, HTMLTokenizer};
+
+const TEST_DIRECTORY: &str = "tests/html5lib-tests/tree-construction";
+
+/// Tests that are skipped because the parser doesn't pass them yet, by file name and `#data`.
+/// Each entry should say why, and be taken out once the test passes
+const KNOWN_FAILURES: &[(&str, &str)] = &[];
+
+#[test]
+fn html5lib_tree_construction_tests() {
+    let mut paths: Vec<_> = fs::read_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join(TEST_DIRECTORY))
+        .expect("the html5lib tree construction tests should be in tests/html5lib-tests/tree-construction")
+        .map(|entry| entry.expect("the test directory should be readable").path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "dat"))
+        .collect();
+    paths.sort();
+
+    let mut failures = Vec::new();
+    let (mut total_passed, mut total_failed, mut total_skipped) = (0, 0, 0);
+    let mut total_errors = 0;
+    for path in paths {
+        let file_name = path.file_name().unwrap().to_string_lossy().into_owned();
+        let contents = fs::read_to_string(&path).unwrap();
+
+        let (mut passed, mut failed, mut skipped) = (0, 0, 0);
+        let mut errors = 0;
+        for test in parse_tests(&contents) {
+            errors += test.error_count();
+            let data = test.section("data").unwrap_or_default();
+            if KNOWN_FAILURES.contains(&(file_name.as_str(), data)) {
+                skipped += 1;
+                continue;
+            }
+            match run_test(&test) {
+                Outcome::Passed => passed += 1,
+                Outcome::Skipped => skipped += 1,
+                Outcome::Failed(failure) => {
+                    failed += 1;
+                    failures.push(format!("{}: {}", file_name, failure));
+                }
+            }
+        }
+        println!(
+            "{:<32} {:>4} passed {:>4} failed {:>4} skipped {:>5} parse errors unchecked",
+            file_name, passed, failed, skipped, errors
+        );
+        total_passed += passed;
+        total_failed += failed;
+        total_skipped += skipped;
+        total_errors += errors;
+    }
+    println!(
+        "{:<32} {:>4} passed {:>4} failed {:>4} skipped {:>5} parse errors unchecked",
+        "total", total_passed, total_failed, total_skipped, total_errors
+    );
+
+    assert!(
+        failures.is_empty(),
+        "{} html5lib tree construction tests failed:\n\n{}",
+        failures.len(),
+        failures.join("\n\n")
+    );
+}
+
+enum Outcome {
+    Passed,
+    Failed(String),
+    // fragment tests and tests that need scripting
+    Skipped,
+}
+
+/// One test from a `.dat` file: the sections in it, like `#data` and `#document`, with their
+/// contents
+struct Test<'a> {
+    sections: Vec<(&'a str, String)>,
+}
+
+impl Test<'_> {
+    fn section(&self, name: &str) -> Option<&str> {
+        self.sections
+            .iter()
+            .find(|(section_name, _)| *section_name == name)
+            .map(|(_, contents)| contents.as_str())
+    }
+
+    /// How many parse errors the test expects, one per line of its `#errors` section
+    fn error_count(&self) -> usize {
+        self.section("errors").map_or(0, |errors| errors.lines().count())
+    }
+}
+
+const SECTION_NAMES: &[&str] = &[
+    "data",
+    "errors",
+    "new-errors",
+    "document-fragment",
+    "script-off",
+    "script-on",
+    "document",
+];
+
+/// Splits a `.dat` file into its tests. Every test starts with a `#data` line, and every section
+/// runs until the next section's `#name` line. The blank line that separates tests isn't part of
+/// the last section
+fn parse_tests(contents: &str) -> Vec<Test<'_>> {
+    let mut tests: Vec<Test> = Vec::new();
+    for line in contents.lines() {
+        if line == "#data" {
+            tests.push(Test { sections: Vec::new() });
+        }
+        let Some(test) = tests.last_mut() else {
+            continue;
+        };
+        match line.strip_prefix('#') {
+            Some(section_name) if SECTION_NAMES.contains(&section_name) => {
+                test.sections.push((section_name, String::new()));
+            }
+            _ => {
+                if let Some((_, section)) = test.sections.last_mut() {
+                    section.push_str(line);
+                    section.push('\n');
+                }
+            }
+        }
+    }
+    for test in &mut tests {
+        if let Some((_, last_section)) = test.sections.last_mut() {
+            while last_section.ends_with("\n\n") {
+                last_section.pop();
+            }
+        }
+        for (_, section) in &mut test.sections {
+            section.pop();
+        }
+    }
+    tests
+}
+
+fn run_test(test: &Test) -> Outcome {
+    if test.section("document-fragment").is_some() || test.section("script-on").is_some() {
+        return Outcome::Skipped;
+    }
+    let input = test.section("data").unwrap_or_default();
+    let expected = test.section("document").unwrap_or_default();
+
+    let document = HTMLParser::new(HTMLTokenizer::new(input)).parse();
+    let actual = document.to_string();
+    let actual = actual
+        .strip_prefix("#document\n")
+        .unwrap_or(&actual)
+        .trim_end_matches('\n');
+
+    if actual == expected {
+        Outcome::Passed
+    } else {
+        Outcome::Failed(format!(
+            "{:?}\n  expected:\n{}\n  actual:\n{}",
+            input, expected, actual
+        ))
+    }
+}
@@ -0,0 +1,145 @@
+//! Tests for the source positions on tokens, attributes and parse errors: the lines, columns and
+//! byte offsets they're given, and the spans slicing the right part of the input.
+//!
+//! Lines and columns are 1-based and columns count characters, offsets are 0-based and count
+//! bytes. A CRLF pair is one line break, like it is once the input has been normalized.
+
+use wonder_land::alice::{
+    Attribute, HTMLToken, HTMLTokenizer, ParseError, SourcePosition, SourceSpan, Tag,
+};
+
+fn position(line: usize, column: usize, offset: usize) -> SourcePosition {
+    SourcePosition {
+        line,
+        column,
+        offset,
+    }
+}
+
+fn span(start: SourcePosition, end: SourcePosition) -> SourceSpan {
+    SourceSpan { start, end }
+}
+
+fn tokens(input: &str) -> Vec<HTMLToken<'_>> {
+    HTMLTokenizer::new(input).collect()
+}
+
+fn attributes<'a>(token: &'a HTMLToken<'_>) -> &'a [Attribute<'a>] {
+    match token {
+        HTMLToken::Tag(Tag::StartTag { attributes, .. }) => attributes,
+        _ => panic!("expected a start tag, got {:?}", token),
+    }
+}
+
+#[test]
+fn advance() {
+    let start = SourcePosition::default();
+    assert_eq!(start, position(1, 1, 0));
+    assert_eq!(start.advance('a'), position(1, 2, 1));
+    assert_eq!(start.advance('\n'), position(2, 1, 1));
+    // columns count characters, offsets count bytes
+    assert_eq!(start.advance('\u{E9}'), position(1, 2, 2));
+    assert_eq!(start.advance('\u{20AC}'), position(1, 2, 3));
+    assert_eq!(start.advance('\u{1F600}'), position(1, 2, 4));
+}
+
+#[test]
+fn advance_over_is_the_same_as_advancing_one_character_at_a_time() {
+    for text in ["", "abc", "a\nb", "\n\n", "h\u{E9}llo\nw\u{F6}rld\n\u{1F600}!", "\u{20AC}\n"] {
+        let start = position(3, 7, 20);
+        let one_at_a_time = text.chars().fold(start, SourcePosition::advance);
+        assert_eq!(start.advance_over(text), one_at_a_time, "{:?}", text);
+    }
+}
+
+#[test]
+fn spans_after_crlf() {
+    let tokens = tokens("a\r\nb<p>");
+    // the CRLF is a single "\n" in the text, but both bytes are in the span
+    assert_eq!(tokens[0].span(), Some(span(position(1, 1, 0), position(2, 2, 4))));
+    assert_eq!(tokens[1].span(), Some(span(position(2, 2, 4), position(2, 5, 7))));
+}
+
+#[test]
+fn spans_after_bare_cr() {
+    let tokens = tokens("a\rb\r\n<p>");
+    assert_eq!(tokens[0].span(), Some(span(position(1, 1, 0), position(3, 1, 5))));
+    assert_eq!(tokens[1].span(), Some(span(position(3, 1, 5), position(3, 4, 8))));
+}
+
+#[test]
+fn spans_after_multibyte_characters() {
+    let tokens = tokens("\u{E9}\u{20AC}\u{1F600}<p>");
+    assert_eq!(tokens[0].span(), Some(span(position(1, 1, 0), position(1, 4, 9))));
+    assert_eq!(tokens[1].span(), Some(span(position(1, 4, 9), position(1, 7, 12))));
+}
+
+#[test]
+fn spans_slice_the_input() {
+    let input = "\u{E9}t\u{E9}\r\n<a href='x'>link</a><!-- c -->";
+    let slices: Vec<&str> = tokens(input)
+        .iter()
+        .filter_map(HTMLToken::span)
+        .map(|span| &input[span.byte_range()])
+        .collect();
+    assert_eq!(
+        slices,
+        ["\u{E9}t\u{E9}\r\n", "<a href='x'>", "link", "</a>", "<!-- c -->"]
+    );
+}
+
+#[test]
+fn attribute_key_and_value_spans() {
+    let tokens = tokens("<a href=\"x\" id=y checked>");
+    let attributes = attributes(&tokens[0]);
+
+    assert_eq!(attributes[0].key_span, span(position(1, 4, 3), position(1, 8, 7)));
+    // quotes aren't part of the value's span
+    assert_eq!(
+        attributes[0].value_span,
+        Some(span(position(1, 10, 9), position(1, 11, 10)))
+    );
+
+    assert_eq!(attributes[1].key_span, span(position(1, 13, 12), position(1, 15, 14)));
+    assert_eq!(
+        attributes[1].value_span,
+        Some(span(position(1, 16, 15), position(1, 17, 16)))
+    );
+
+    assert_eq!(attributes[2].key_span, span(position(1, 18, 17), position(1, 25, 24)));
+    assert_eq!(attributes[2].value_span, None);
+}
+
+#[test]
+fn attribute_spans_after_crlf_and_multibyte_characters() {
+    let input = "\u{1F600}\r\n\r\n<br \r\nclass='\u{E9}t\u{E9}'>";
+    let tokens = tokens(input);
+    assert_eq!(tokens[1].span(), Some(span(position(3, 1, 8), position(4, 13, 28))));
+
+    let attributes = attributes(&tokens[1]);
+    assert_eq!(attributes[0].key_span, span(position(4, 1, 14), position(4, 6, 19)));
+    let value_span = attributes[0].value_span.unwrap();
+    assert_eq!(value_span, span(position(4, 8, 21), position(4, 11, 26)));
+    assert_eq!(&input[value_span.byte_range()], "\u{E9}t\u{E9}");
+}
+
+#[test]
+fn parse_error_positions() {
+    let cases = [
+        (
+            "a\r\n\u{E9}<p =x>",
+            ParseError::UnexpectedEqualsSignBeforeAttributeName,
+            position(2, 5, 8),
+        ),
+        ("\r\n\r\n\u{20AC}\0", ParseError::UnexpectedNullCharacter, position(3, 2, 7)),
+        ("\u{E9}\r\n</p x>", ParseError::EndTagWithAttributes, position(2, 6, 9)),
+    ];
+    for (input, error, expected_position) in cases {
+        let mut tokenizer = HTMLTokenizer::new(input);
+        while tokenizer.next_token().is_some() {}
+        let reports = tokenizer.parse_errors();
+        assert_eq!(reports.len(), 1, "{:?}: {:?}", input, reports);
+        assert_eq!(reports[0].error, error, "{:?}", input);
+        assert_eq!(reports[0].position, expected_position, "{:?}", input);
+    }
+}